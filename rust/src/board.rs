@@ -14,126 +14,834 @@
 extern crate rand;
 
 use rand::Rng; // For shuffling a deck of cards
+use std::error;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::str::FromStr;
 
-/// Number of suits (4)
-pub const SUITS: usize = 4;
+/// Maximum number of suits (4)
+pub const MAX_SUITS: usize = 4;
 
-/// Number of stacks of cards
-pub const STACKS: usize = 2 * SUITS;
+/// Maximum number of stacks of cards
+pub const MAX_STACKS: usize = 16;
+
+/// Number of stacks of cards in the usual game
+pub const DEFAULT_STACKS: usize = 8;
 
 /// Maximum number of ranks (13)
 pub const MAX_RANKS: usize = 13;
 
-/// A card is an unsigned integer less than 56.  Cards less than 4 are
-/// blank cards used to represent the absence of a card in a given suit.
-/// For all cards c, c == card2suit(c) * card2rank(c).
-pub type Card = usize;
+/// Maximum number of reserve cells
+pub const MAX_RESERVES: usize = 4;
+
+/// Maximum number of decks shuffled together
+pub const MAX_DECKS: usize = 2;
+
+/// A suit
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Suit {
+    Clubs,
+    Diamonds,
+    Hearts,
+    Spades,
+}
+
+impl Suit {
+    /// The suits in order
+    pub const ALL: [Suit; MAX_SUITS] =
+        [Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades];
+
+    /// Return the suit with the given index (None if not less than 4)
+    pub fn new(i: usize) -> Option<Suit> {
+        Suit::ALL.get(i).copied()
+    }
+
+    /// The index of the suit, an unsigned integer less than 4
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Show suit as a char
+    pub fn to_char(self) -> char {
+        match self {
+            Suit::Clubs => 'C',
+            Suit::Diamonds => 'D',
+            Suit::Hearts => 'H',
+            Suit::Spades => 'S',
+        }
+    }
+
+    /// Is the suit red?
+    pub fn is_red(self) -> bool {
+        matches!(self, Suit::Diamonds | Suit::Hearts)
+    }
+
+    /// Show suit as a Unicode glyph
+    ///
+    /// Writing a glyph on the screen requires wide character output,
+    /// as `Window::addch` munges it.
+    pub fn to_glyph(self) -> char {
+        match self {
+            Suit::Clubs => '♣',    // U+2663
+            Suit::Diamonds => '♦', // U+2666
+            Suit::Hearts => '♥',   // U+2665
+            Suit::Spades => '♠',   // U+2660
+        }
+    }
+
+    /// Read a suit from a char
+    pub fn from_char(ch: char) -> Option<Suit> {
+        Suit::ALL
+            .iter()
+            .copied()
+            .find(|s| s.to_char() == ch.to_ascii_uppercase())
+    }
+}
+
+/// A rank
+///
+/// The ace has the smallest rank and the king has the greatest rank.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Rank {
+    Ace = 1,
+    Two,
+    Three,
+    Four,
+    Five,
+    Six,
+    Seven,
+    Eight,
+    Nine,
+    Ten,
+    Jack,
+    Queen,
+    King,
+}
+
+impl Rank {
+    /// The ranks in order
+    pub const ALL: [Rank; MAX_RANKS] = [
+        Rank::Ace,
+        Rank::Two,
+        Rank::Three,
+        Rank::Four,
+        Rank::Five,
+        Rank::Six,
+        Rank::Seven,
+        Rank::Eight,
+        Rank::Nine,
+        Rank::Ten,
+        Rank::Jack,
+        Rank::Queen,
+        Rank::King,
+    ];
+
+    /// Return the rank with the given value (None unless between 1
+    /// and 13)
+    pub fn new(n: usize) -> Option<Rank> {
+        match n {
+            0 => None,
+            n => Rank::ALL.get(n - 1).copied(),
+        }
+    }
+
+    /// The value of a rank, an unsigned integer between 1 and 13
+    pub fn value(self) -> usize {
+        self as usize
+    }
+
+    /// The next higher rank (None for a king)
+    pub fn succ(self) -> Option<Rank> {
+        Rank::new(self.value() + 1)
+    }
+
+    /// The next lower rank (None for an ace)
+    pub fn pred(self) -> Option<Rank> {
+        Rank::new(self.value() - 1)
+    }
+
+    /// Show rank as a char
+    pub fn to_char(self) -> char {
+        b"A23456789TJQK"[self.value() - 1] as char
+    }
+
+    /// Read a rank from a char
+    pub fn from_char(ch: char) -> Option<Rank> {
+        Rank::ALL
+            .iter()
+            .copied()
+            .find(|r| r.to_char() == ch.to_ascii_uppercase())
+    }
+}
+
+/// A card
+///
+/// A card is stored in a byte using the number that represented it
+/// in version 1.3, its rank times 4 plus the index of its suit.  In
+/// a game with more than one deck, the copies of a card are equal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Card(u8);
+
+impl Card {
+    /// Create a card
+    pub fn new(rank: Rank, suit: Suit) -> Card {
+        Card((rank.value() * MAX_SUITS + suit.index()) as u8)
+    }
+
+    /// The suit of the card
+    pub fn suit(self) -> Suit {
+        Suit::ALL[self.0 as usize % MAX_SUITS]
+    }
+
+    /// The rank of the card
+    pub fn rank(self) -> Rank {
+        Rank::ALL[self.0 as usize / MAX_SUITS - 1]
+    }
+
+    /// The number that represents the card in a saved game
+    pub fn code(self) -> u8 {
+        self.0
+    }
+
+    /// Return the card represented by a number in a saved game
+    pub fn from_code(code: usize) -> Option<Card> {
+        let rank = Rank::new(code / MAX_SUITS)?;
+        let suit = Suit::new(code % MAX_SUITS)?;
+        Some(Card::new(rank, suit))
+    }
+
+    /// Return an iterator for the cards in a deck with the given
+    /// number of suits and ranks
+    ///
+    /// The cards come in order of rank, and within a rank, in order
+    /// of suit.  The suits used are the first ones in [`Suit::ALL`].
+    pub fn deck(suits: usize, ranks: usize) -> impl Iterator<Item = Card> {
+        let suits = &Suit::ALL[..suits];
+        Rank::ALL[..ranks]
+            .iter()
+            .flat_map(move |r| suits.iter().map(move |s| Card::new(*r, *s)))
+    }
+
+    /// Is the card in a deck with a given number of suits and ranks?
+    pub fn in_deck(self, suits: usize, ranks: usize) -> bool {
+        self.rank().value() <= ranks && self.suit().index() < suits
+    }
+}
+
+/// Cards are written rank first, as in TH for the ten of hearts.  The
+/// alternate form, as in HT, is the one used on the screen.
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rank = self.rank().to_char();
+        let suit = self.suit().to_char();
+        if f.alternate() {
+            write!(f, "{}{}", suit, rank)
+        } else {
+            write!(f, "{}{}", rank, suit)
+        }
+    }
+}
+
+/// A card is read from a rank and a suit in either order, so both TH
+/// and HT are the ten of hearts.
+impl FromStr for Card {
+    type Err = ParseCardError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next(), chars.next()) {
+            (Some(a), Some(b), None) => {
+                match (Rank::from_char(a), Suit::from_char(b)) {
+                    (Some(r), Some(s)) => Ok(Card::new(r, s)),
+                    _ => match (Suit::from_char(a), Rank::from_char(b)) {
+                        (Some(s), Some(r)) => Ok(Card::new(r, s)),
+                        _ => Err(ParseCardError),
+                    },
+                }
+            }
+            _ => Err(ParseCardError),
+        }
+    }
+}
+
+/// The error returned when a string does not name a card
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseCardError;
+
+impl fmt::Display for ParseCardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Not a card")
+    }
+}
+
+impl error::Error for ParseCardError {}
+
+/// Largest deal number chosen at random
+pub const MAX_RANDOM_DEAL: u32 = 999_999;
+
+/// Choose a deal number at random
+pub fn random_deal() -> u32 {
+    rand::thread_rng().gen_range(1, MAX_RANDOM_DEAL + 1)
+}
+
+/// A source of random numbers used to shuffle a deck
+pub trait Random {
+    /// Return a number less than n with every number equally likely
+    fn below(&mut self, n: u32) -> u32;
+}
+
+/// Shuffle a slice so that every permutation is equally likely
+///
+/// This is the Fisher-Yates shuffle used by version 1.3.  Going from
+/// the end of the slice, each position is swapped with a position
+/// chosen from the ones not yet fixed.
+pub fn shuffle<T>(rng: &mut impl Random, v: &mut [T]) {
+    for i in (1..v.len()).rev() {
+        let j = rng.below(i as u32 + 1) as usize;
+        v.swap(i, j);
+    }
+}
 
-/// The suit associated with a card
+/// The pseudo-random number generator used to shuffle a deck
 ///
-/// The suit associated with a card is an unsigned integer less than
-/// 4.
-pub fn card2suit(c: Card) -> usize {
-    c % SUITS
+/// A deal number must give the same layout on every platform and in
+/// every version of this program, so the generator is specified here
+/// rather than taken from a library.  It is the 64-bit linear
+/// congruential generator from Knuth's MMIX, and it uses the high 32
+/// bits of its state.  Do not change it, as that would change every
+/// deal.
+pub struct DealRng {
+    state: u64,
+}
+
+impl DealRng {
+    /// Create a generator seeded by a deal number
+    pub fn new(n: u32) -> DealRng {
+        let mut rng = DealRng { state: n as u64 };
+        rng.next_u32(); // Mix the seed into the state
+        rng
+    }
+
+    /// Return the next 32 bits
+    pub fn next_u32(&mut self) -> u32 {
+        self.state = self
+            .state
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (self.state >> 32) as u32
+    }
+}
+
+impl Random for DealRng {
+    /// Numbers from the top of the range that would favor small
+    /// results are rejected.
+    fn below(&mut self, n: u32) -> u32 {
+        let zone = u32::MAX - u32::MAX % n;
+        loop {
+            let r = self.next_u32();
+            if r < zone {
+                return r % n;
+            }
+        }
+    }
+}
+
+/// Number used to identify a saved game
+pub const MAGIC_NUMBER: i32 = 13921;
+
+/// Number used to identify a saved game that records its variant
+pub const VARIANT_MAGIC_NUMBER: i32 = 13922;
+
+/// The games that can be played on a board
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Variant {
+    /// Every card is dealt to the stacks
+    #[default]
+    StreetsAndAlleys,
+    /// The aces start on the foundations
+    BeleagueredCastle,
+    /// Beleaguered Castle with cards played to the foundations as
+    /// they are dealt
+    Citadel,
+    /// Cards are built up or down in suit
+    Fortress,
+    /// Fortress with a foundation base rank chosen by the player and
+    /// ranks that wrap around
+    Chessboard,
+    /// Streets and Alleys with a reserve cell
+    Stronghold,
+    /// Fortress with a reserve cell
+    FortressReserve,
+}
+
+/// The ways cards are dealt to the stacks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DealMode {
+    /// Each card goes to the next stack in turn
+    RoundRobin,
+    /// A card that can go to its foundation is played there instead
+    /// of being placed on the next stack
+    Citadel,
+}
+
+impl Variant {
+    /// All variants in the order of their numbers in save files
+    pub const ALL: [Variant; 7] = [
+        Variant::StreetsAndAlleys,
+        Variant::BeleagueredCastle,
+        Variant::Citadel,
+        Variant::Fortress,
+        Variant::Chessboard,
+        Variant::Stronghold,
+        Variant::FortressReserve,
+    ];
+
+    /// The variant with a given number (None if out of range)
+    pub fn new(i: usize) -> Option<Variant> {
+        Variant::ALL.get(i).copied()
+    }
+
+    /// The number used for the variant in save files
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// The name of the game shown in the title
+    pub fn name(self) -> &'static str {
+        match self {
+            Variant::StreetsAndAlleys => "Streets and Alleys",
+            Variant::BeleagueredCastle => "Beleaguered Castle",
+            Variant::Citadel => "Citadel",
+            Variant::Fortress => "Fortress",
+            Variant::Chessboard => "Chessboard",
+            Variant::Stronghold => "Stronghold",
+            Variant::FortressReserve => "Fortress with Reserve",
+        }
+    }
+
+    /// The name of the variant used on the command line
+    pub fn option_name(self) -> &'static str {
+        match self {
+            Variant::StreetsAndAlleys => "streets",
+            Variant::BeleagueredCastle => "castle",
+            Variant::Citadel => "citadel",
+            Variant::Fortress => "fortress",
+            Variant::Chessboard => "chessboard",
+            Variant::Stronghold => "stronghold",
+            Variant::FortressReserve => "fortress-reserve",
+        }
+    }
+
+    /// Do the aces start on the foundations?
+    pub fn aces_up(self) -> bool {
+        matches!(self, Variant::BeleagueredCastle | Variant::Citadel)
+    }
+
+    /// The way the cards are dealt
+    pub fn deal_mode(self) -> DealMode {
+        match self {
+            Variant::Citadel => DealMode::Citadel,
+            _ => DealMode::RoundRobin,
+        }
+    }
+
+    /// The number of reserve cells used unless another is requested
+    pub fn reserves(self) -> usize {
+        match self {
+            Variant::Stronghold | Variant::FortressReserve => 1,
+            _ => 0,
+        }
+    }
+
+    /// The number of stacks used unless another is requested
+    pub fn stacks(self) -> usize {
+        match self {
+            Variant::Fortress
+            | Variant::Chessboard
+            | Variant::FortressReserve => 10,
+            _ => DEFAULT_STACKS,
+        }
+    }
+
+    /// The rules for building on the stacks and the foundations
+    pub fn rules(self) -> Rules {
+        match self {
+            Variant::Fortress | Variant::FortressReserve => Rules {
+                build: Build::UpOrDownInSuit,
+                base: Some(Rank::Ace),
+                wrap: false,
+            },
+            Variant::Chessboard => Rules {
+                build: Build::UpOrDownInSuit,
+                base: None,
+                wrap: true,
+            },
+            _ => Rules {
+                build: Build::Down,
+                base: Some(Rank::Ace),
+                wrap: false,
+            },
+        }
+    }
+}
+
+/// The ways a card may be placed on the top card of a stack
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Build {
+    /// On a card of any suit with rank one greater
+    Down,
+    /// On a card of the same suit with rank one greater or one less
+    UpOrDownInSuit,
+}
+
+/// The rules of a variant that a board consults
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rules {
+    /// How cards are built on the stacks
+    pub build: Build,
+    /// The rank of the first card of each foundation, or None when
+    /// the first card moved to any foundation chooses it
+    pub base: Option<Rank>,
+    /// Does the largest rank in use wrap around to the ace?
+    pub wrap: bool,
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Variants are read using their command line names.
+impl FromStr for Variant {
+    type Err = ParseVariantError;
+
+    fn from_str(s: &str) -> Result<Variant, ParseVariantError> {
+        Variant::ALL
+            .iter()
+            .find(|v| v.option_name().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or(ParseVariantError)
+    }
 }
 
-/// The rank associated with a card
+/// The error returned when a string does not name a variant
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseVariantError;
+
+impl fmt::Display for ParseVariantError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Bad variant name")
+    }
+}
+
+impl error::Error for ParseVariantError {}
+
+/// A move of the card on the top of a stack or in a reserve cell
 ///
-/// The rank associated with a card is an unsigned integer less than
-/// 14.  A king is has rank 13, and a blank card has rank 0.
-pub fn card2rank(c: Card) -> usize {
-    c / SUITS
-}
-
-/// Show suit as a char
-pub fn show_suit(c: Card) -> char {
-    match card2suit(c) {
-        0 => 'C',
-        1 => 'D',
-        2 => 'H',
-        3 => 'S',
-        _ => panic!("bad suit"),
-    }
-}
-
-// Wish I could use this, but w.addch munges it.
-//
-// pub fn show_suit(c: Card) -> char {
-//     match card2suit(c) {
-//         0 => '♣', // U+2663
-//         1 => '♦', // U+2666
-//         2 => '♥', // U+2665
-//         3 => '♠', // U+2660
-//         _ => panic!("bad suit"),
-//     }
-// }
-
-/// Show rank as a char
-pub fn show_rank(c: Card) -> char {
-    match card2rank(c) {
-        0 => '-',
-        1 => 'A',
-        2 => '2',
-        3 => '3',
-        4 => '4',
-        5 => '5',
-        6 => '6',
-        7 => '7',
-        8 => '8',
-        9 => '9',
-        10 => 'T',
-        11 => 'J',
-        12 => 'Q',
-        13 => 'K',
-        _ => panic!("bad rank"),
+/// Moves name places.  A place is either the index of a stack, or
+/// the number of stacks plus the index of a reserve cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Move {
+    /// Move to the top of another stack or to a reserve cell (from,
+    /// to)
+    Stack(usize, usize),
+    /// Move to the foundation of the card's suit (from)
+    Foundation(usize),
+}
+
+impl Move {
+    /// The place from which a card is moved
+    pub fn source(self) -> usize {
+        match self {
+            Move::Stack(from, _) => from,
+            Move::Foundation(from) => from,
+        }
     }
 }
 
+// A move that has been made along with what is needed to take it
+// back.
+#[derive(Clone, Copy, Debug)]
+struct Made {
+    m: Move,
+    card: Card,          // The card moved
+    foundation: usize,   // The foundation of a foundation move
+    prior: Option<Card>, // Its foundation before the move
+    joined: bool,        // Made as part of the move before it
+}
+
 /// A Streets and Alleys board
+#[derive(Clone)]
 pub struct Board {
+    variant: Variant,
+    rules: Rules,
+    base: Option<Rank>, // Rank of the first card of each foundation
+    decks: usize,
+    suits: usize,
     ranks: usize,
-    stack: [Vec<Card>; STACKS],
-    foundation: [Card; SUITS],
+    stack: Vec<Vec<Card>>,
+    foundation: Vec<Option<Card>>, // Suits repeated for each deck
+    reserve: Vec<Option<Card>>,
+    deal: Option<u32>, // Deal number
+    done: Vec<Made>,   // Moves that can be undone
+    undone: Vec<Made>, // Moves that can be redone
 }
 
 impl Board {
-    /// Create a fresh board using a given ranks worth of cards
+    /// Create a fresh board for a variant using a given number of
+    /// suits, stacks, and ranks
+    ///
+    /// The board has a foundation for each suit and the reserve cells
+    /// of the variant.  The usual game has 4 suits and 8 stacks.
+    pub fn new(
+        variant: Variant,
+        suits: usize,
+        stacks: usize,
+        ranks: usize,
+    ) -> Board {
+        Board::with_reserves(variant, suits, stacks, ranks, variant.reserves())
+    }
+
+    /// Create a fresh board with a given number of reserve cells
+    ///
+    /// Each reserve cell holds at most one card.  Adding reserve
+    /// cells to a variant that has none makes for an easier game
+    /// that is good for practice.
+    pub fn with_reserves(
+        variant: Variant,
+        suits: usize,
+        stacks: usize,
+        ranks: usize,
+        reserves: usize,
+    ) -> Board {
+        Board::with_decks(variant, 1, suits, stacks, ranks, reserves)
+    }
+
+    /// Create a fresh board holding a number of decks shuffled
+    /// together
     ///
-    /// The board has 8 stacks and 4 foundations.
-    pub fn new(ranks: usize) -> Board {
+    /// There is a foundation for each suit of each deck, so two decks
+    /// of 4 suits give 8 foundations.  A card may go to any foundation
+    /// of its suit, and the copies of a card are interchangeable.
+    pub fn with_decks(
+        variant: Variant,
+        decks: usize,
+        suits: usize,
+        stacks: usize,
+        ranks: usize,
+        reserves: usize,
+    ) -> Board {
+        if decks == 0 || decks > MAX_DECKS {
+            panic!("bad number of decks when creating a board")
+        }
+        if suits == 0 || suits > MAX_SUITS {
+            panic!("bad number of suits when creating a board")
+        }
+        if stacks == 0 || stacks > MAX_STACKS {
+            panic!("bad number of stacks when creating a board")
+        }
+        if reserves > MAX_RESERVES {
+            panic!("bad number of reserve cells when creating a board")
+        }
         if ranks <= MAX_RANKS {
-            let mut b = Board {
+            let depth = (decks * suits * ranks).div_ceil(stacks);
+            Board {
+                variant,
+                rules: variant.rules(),
+                base: variant.rules().base,
+                decks,
+                suits,
                 ranks,
-                stack: [
-                    Vec::with_capacity(ranks),
-                    Vec::with_capacity(ranks),
-                    Vec::with_capacity(ranks),
-                    Vec::with_capacity(ranks),
-                    Vec::with_capacity(ranks),
-                    Vec::with_capacity(ranks),
-                    Vec::with_capacity(ranks),
-                    Vec::with_capacity(ranks),
-                ],
-                foundation: [0; SUITS],
-            };
-            for i in 0..SUITS {
-                // Add blank cards
-                b.foundation[i] = i;
+                stack: vec![Vec::with_capacity(depth + ranks); stacks],
+                foundation: vec![None; decks * suits],
+                reserve: vec![None; reserves],
+                deal: None,
+                done: Vec::new(),
+                undone: Vec::new(),
             }
-            b
         } else {
             panic!("bad number of ranks when creating a board")
         }
     }
 
-    /// Is the game done?
-    pub fn is_done(&self) -> bool {
-        for s in self.stack.iter() {
-            if !is_stack_done(s) {
-                return false;
+    /// Return the variant played on the board
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    /// Return the rules consulted by the board
+    pub fn rules(&self) -> Rules {
+        self.rules
+    }
+
+    /// Return the rank of the first card of each foundation
+    ///
+    /// When the rules let the player choose, it is None until a card
+    /// is moved to a foundation.
+    pub fn base(&self) -> Option<Rank> {
+        self.base
+    }
+
+    /// Return the number of ranks used by the board
+    pub fn ranks(&self) -> usize {
+        self.ranks
+    }
+
+    /// Return the number of decks shuffled together
+    pub fn decks(&self) -> usize {
+        self.decks
+    }
+
+    /// Return the number of suits used by the board
+    pub fn suits(&self) -> usize {
+        self.suits
+    }
+
+    /// Return the suits used by the board
+    ///
+    /// They are the first ones in [`Suit::ALL`].
+    pub fn suits_used(&self) -> &'static [Suit] {
+        &Suit::ALL[..self.suits]
+    }
+
+    /// Return the number of foundations, one for each suit of each
+    /// deck
+    pub fn foundations(&self) -> usize {
+        self.foundation.len()
+    }
+
+    /// Return the suit of a foundation
+    pub fn foundation_suit(&self, f: usize) -> Suit {
+        Suit::ALL[f % self.suits]
+    }
+
+    /// Return the number of stacks
+    pub fn stacks(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Return the number of reserve cells
+    pub fn reserves(&self) -> usize {
+        self.reserve.len()
+    }
+
+    /// Return the number of places, the stacks followed by the
+    /// reserve cells
+    pub fn places(&self) -> usize {
+        self.stacks() + self.reserve.len()
+    }
+
+    /// Is the place a reserve cell?
+    pub fn is_reserve(&self, p: usize) -> bool {
+        p >= self.stacks()
+    }
+
+    /// Return the card in a reserve cell (None if empty)
+    pub fn reserve_ref(&self, i: usize) -> Option<Card> {
+        self.reserve[i]
+    }
+
+    /// Return the key that selects a place on the screen
+    ///
+    /// Stacks are selected by 1 to 9 and then by capital letters
+    /// starting with A, and reserve cells by letters starting with a.
+    pub fn place_label(&self, p: usize) -> char {
+        if self.is_reserve(p) {
+            reserve_name(p - self.stacks())
+        } else {
+            stack_label(p)
+        }
+    }
+
+    /// Return the place selected by a key, if any
+    pub fn label_place(&self, c: char) -> Option<usize> {
+        (0..self.places()).find(|p| self.place_label(*p) == c)
+    }
+
+    // The rank that follows a rank, if any
+    fn next_rank(&self, r: Rank) -> Option<Rank> {
+        if r.value() < self.ranks {
+            r.succ()
+        } else if self.rules.wrap {
+            Some(Rank::Ace)
+        } else {
+            None
+        }
+    }
+
+    // The rank that comes before a rank, if any
+    fn prev_rank(&self, r: Rank) -> Option<Rank> {
+        if r != Rank::Ace {
+            r.pred()
+        } else if self.rules.wrap {
+            Rank::new(self.ranks)
+        } else {
+            None
+        }
+    }
+
+    // The position of a rank counting up from the base, starting at
+    // one.  The ace is the base until one is chosen.
+    fn height(&self, r: Rank) -> usize {
+        let base = self.base.unwrap_or(Rank::Ace).value();
+        (r.value() + self.ranks - base) % self.ranks + 1
+    }
+
+    // The number of cards in a foundation
+    fn foundation_height(&self, f: usize) -> usize {
+        self.foundation[f].map_or(0, |c| self.height(c.rank()))
+    }
+
+    // May a card be placed on the top card of a stack?
+    fn builds_on(&self, c: Card, d: Card) -> bool {
+        let next = self.next_rank(c.rank()) == Some(d.rank());
+        match self.rules.build {
+            Build::Down => next,
+            Build::UpOrDownInSuit => {
+                c.suit() == d.suit()
+                    && (next || self.prev_rank(c.rank()) == Some(d.rank()))
+            }
+        }
+    }
+
+    // The first foundation on which a card may be placed, if any.
+    // With more than one deck, either copy of the next card of a
+    // suit may go on any foundation of the suit that awaits it.
+    fn foundation_for(&self, c: Card) -> Option<usize> {
+        (0..self.foundations()).find(|f| {
+            self.foundation_suit(*f) == c.suit()
+                && self.follows_on_foundation(self.foundation[*f], c)
+        })
+    }
+
+    // May a card go on a foundation with the given top card?  A full
+    // foundation takes no more cards, even when ranks wrap around and
+    // a second deck holds another copy of its base card.
+    fn follows_on_foundation(&self, top: Option<Card>, c: Card) -> bool {
+        match top {
+            Some(d) => {
+                self.height(d.rank()) < self.ranks
+                    && self.next_rank(d.rank()) == Some(c.rank())
             }
+            None => self.base.is_none_or(|r| r == c.rank()),
         }
-        true
+    }
+
+    /// Is the game done?
+    ///
+    /// The game is done when no card is covered by a card of greater
+    /// rank counting up from the base, because then every card can
+    /// be moved to its foundation.  Cards in reserve cells are never
+    /// covered.
+    pub fn is_done(&self) -> bool {
+        self.stack.iter().all(|s| self.is_stack_done(s))
+    }
+
+    // A stack is done if no card is out of order.
+    fn is_stack_done(&self, s: &[Card]) -> bool {
+        s.windows(2)
+            .all(|w| self.height(w[0].rank()) > self.height(w[1].rank()))
     }
 
     /// Add a card to the top of a stack
@@ -151,27 +859,52 @@ impl Board {
         self.stack[s].pop()
     }
 
-    /// Return the card on the top of a stack (None if empty)
-    pub fn last_card(&self, s: usize) -> Option<Card> {
-        match self.stack[s].last() {
-            Some(c) => Some(*c),
-            None => None,
+    /// Return the card on the top of a stack or in a reserve cell
+    /// (None if empty)
+    pub fn last_card(&self, p: usize) -> Option<Card> {
+        if self.is_reserve(p) {
+            self.reserve[p - self.stacks()]
+        } else {
+            self.stack[p].last().copied()
+        }
+    }
+
+    // Remove the card from a place
+    fn take_card(&mut self, p: usize) -> Option<Card> {
+        if self.is_reserve(p) {
+            let i = p - self.stacks();
+            self.reserve[i].take()
+        } else {
+            self.stack[p].pop()
+        }
+    }
+
+    // Put a card in a place
+    fn put_card(&mut self, p: usize, c: Card) {
+        if self.is_reserve(p) {
+            let i = p - self.stacks();
+            self.reserve[i] = Some(c)
+        } else {
+            self.stack[p].push(c)
         }
     }
 
     /// Return an iterator for the cards in a stack
-    pub fn stack_iter(&self, s: usize) -> std::slice::Iter<usize> {
+    pub fn stack_iter(&self, s: usize) -> std::slice::Iter<'_, Card> {
         self.stack[s].iter()
     }
 
-    /// Return the top card in a foundation
-    pub fn foundation_ref(&self, r: usize) -> Card {
-        self.foundation[r]
+    /// Return the top card in a foundation (None if empty)
+    ///
+    /// The foundations for the suits of the first deck come first,
+    /// followed by those of the next deck.
+    pub fn foundation_ref(&self, f: usize) -> Option<Card> {
+        self.foundation[f]
     }
 
     /// Set the top card in a foundation
-    pub fn foundation_set(&mut self, r: usize, c: Card) {
-        self.foundation[r] = c
+    pub fn foundation_set(&mut self, f: usize, c: Option<Card>) {
+        self.foundation[f] = c
     }
 
     /// Clear a board making it ready for a new deal
@@ -179,48 +912,1579 @@ impl Board {
         for s in &mut self.stack {
             s.clear();
         }
-        for i in 0..SUITS {
-            self.foundation[i] = i;
+        for f in &mut self.foundation {
+            *f = None;
         }
+        for r in &mut self.reserve {
+            *r = None;
+        }
+        self.base = self.rules.base;
+        self.deal = None;
+        self.done.clear();
+        self.undone.clear();
     }
 
-    /// Create and Shuffle the cards in a board
+    /// Check a move against the rules of the game
     ///
-    /// Assumes the board is freshly made or has been cleared.
-    pub fn deal(&mut self) {
-        let size = self.ranks * SUITS;
-        let mut deck: Vec<Card> = Vec::with_capacity(size);
-
-        // Create cards
-        for i in 0..size {
-            deck.push(i + SUITS);
+    /// A card may be moved to a foundation of its suit when the card's
+    /// predecessor of the same suit is there, or when the foundation is
+    /// empty and the card has the base rank.  A card may be moved to a
+    /// stack when the rules let it be built on the top card of the
+    /// stack.  A card can always be moved to an empty stack or an empty
+    /// reserve cell.  Returns the card that would be moved.
+    pub fn check(&self, m: Move) -> Result<Card, MoveError> {
+        let from = m.source();
+        if from >= self.places() {
+            return Err(MoveError::NoStack(from));
         }
-
-        // Shuffle
-        for i in 0..size {
-            let j = rand::thread_rng().gen_range(0, size);
-            deck.swap(i, j);
+        let c = self.last_card(from).ok_or(if self.is_reserve(from) {
+            MoveError::EmptyReserve(from - self.stacks())
+        } else {
+            MoveError::NoCard(from)
+        })?;
+        match m {
+            Move::Stack(_, to) if to >= self.places() => {
+                Err(MoveError::NoStack(to))
+            }
+            Move::Stack(_, to) if self.is_reserve(to) => {
+                match self.last_card(to) {
+                    Some(_) => Err(MoveError::FullReserve(to - self.stacks())),
+                    None => Ok(c),
+                }
+            }
+            Move::Stack(_, to) => match self.last_card(to) {
+                Some(d) if !self.builds_on(c, d) => {
+                    Err(MoveError::NotBuildable(c, d))
+                }
+                _ => Ok(c),
+            },
+            Move::Foundation(_) => {
+                if self.foundation_for(c).is_some() {
+                    Ok(c)
+                } else {
+                    Err(MoveError::NotNext(c))
+                }
+            }
         }
+    }
 
-        // Deal cards
-        for (i, c) in deck.iter().enumerate() {
-            self.push_card(i % STACKS, *c);
-        }
+    /// Is a move allowed by the rules of the game?
+    pub fn is_legal(&self, m: Move) -> bool {
+        self.check(m).is_ok()
     }
-}
 
-// A stack is done if no card is out of order.
-fn is_stack_done(s: &[usize]) -> bool {
-    match s.len() {
-        0 => true,
-        1 => true,
-        n => {
-            for i in 1..n {
-                if card2rank(s[i - 1]) <= card2rank(s[i]) {
-                    return false;
+    /// Make a move if it is allowed by the rules of the game
+    ///
+    /// The move is recorded so that it can be undone.  Returns the
+    /// card moved.
+    pub fn apply(&mut self, m: Move) -> Result<Card, MoveError> {
+        self.check(m)?;
+        Ok(self.make_move(m))
+    }
+
+    /// Return the moves allowed by the rules of the game
+    ///
+    /// Moves to a foundation come before moves to a stack or a
+    /// reserve cell.
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::new();
+        for from in 0..self.places() {
+            if self.is_legal(Move::Foundation(from)) {
+                moves.push(Move::Foundation(from));
+            }
+        }
+        for from in 0..self.places() {
+            for to in 0..self.places() {
+                if from != to && self.is_legal(Move::Stack(from, to)) {
+                    moves.push(Move::Stack(from, to));
                 }
             }
-            true
+        }
+        moves
+    }
+
+    /// Return a move to a foundation that can never hurt, if any
+    ///
+    /// When building down in any suit, a card is of no further use
+    /// on a stack once every card of the next lower rank is in a
+    /// foundation.  The first two cards of a foundation are always
+    /// safe to move, because the first is better off in its
+    /// foundation than on the second.  When building in suit, the
+    /// only cards that may be placed on a card that can go to its
+    /// foundation are its successor, which can then go to the
+    /// foundation as well, and its predecessor, which is already
+    /// there.  When ranks wrap around, the predecessor of the base
+    /// card is the last card of its suit, which is not there, so the
+    /// base card is not safe to move.  With more than one deck, the
+    /// other copy of the successor may want the card, so no move is
+    /// safe when building in suit.  Choosing the base rank is never
+    /// safe.
+    pub fn safe_move(&self) -> Option<Move> {
+        self.base?; // Choosing the base rank is never safe
+        (0..self.places()).map(Move::Foundation).find(|m| {
+            match self.check(*m) {
+                Ok(c) => match self.rules.build {
+                    Build::Down => {
+                        let h = self.height(c.rank());
+                        h <= 2
+                            || (0..self.foundations())
+                                .all(|f| self.foundation_height(f) + 1 >= h)
+                    }
+                    Build::UpOrDownInSuit => {
+                        self.decks == 1
+                            && (!self.rules.wrap || self.height(c.rank()) > 1)
+                    }
+                },
+                Err(_) => false,
+            }
+        })
+    }
+
+    /// Return the number of cards in the ordered run on the top of a
+    /// stack
+    ///
+    /// In an ordered run, each card may be built on the card below
+    /// it.
+    pub fn run_len(&self, s: usize) -> usize {
+        let stack = &self.stack[s];
+        match stack.len() {
+            0 => 0,
+            n => {
+                1 + (1..n)
+                    .rev()
+                    .take_while(|i| self.builds_on(stack[*i], stack[*i - 1]))
+                    .count()
+            }
+        }
+    }
+
+    /// Plan a move of part of the ordered run on the top of a stack
+    /// to another stack
+    ///
+    /// When the other stack has cards, the part of the run that can
+    /// be placed on its top card is moved.  Otherwise as much of the
+    /// run as possible is moved.  Since cards may only be moved one
+    /// at a time, the plan is a sequence of single card moves that
+    /// uses the empty stacks and reserve cells to hold cards along
+    /// the way.  With n empty stacks and r empty reserve cells, a run
+    /// of up to r + 1 times 2 to the n cards can be moved.
+    pub fn plan_run(
+        &self,
+        from: usize,
+        to: usize,
+    ) -> Result<Vec<Move>, MoveError> {
+        if from >= self.stacks() {
+            return Err(MoveError::NoStack(from));
+        }
+        let c = self.last_card(from).ok_or(MoveError::NoCard(from))?;
+        if to >= self.stacks() {
+            return Err(MoveError::NoStack(to));
+        }
+        if from == to {
+            return Err(MoveError::SameStack(from));
+        }
+        let free: Vec<usize> = (0..self.stacks())
+            .filter(|s| *s != to && self.stack_len(*s) == 0)
+            .collect();
+        let cells: Vec<usize> = (self.stacks()..self.places())
+            .filter(|p| self.last_card(*p).is_none())
+            .collect();
+        let room = (cells.len() + 1) << free.len();
+        let run = self.run_len(from);
+        let n = match self.last_card(to) {
+            None => run.min(room),
+            Some(d) => {
+                let stack = &self.stack[from];
+                let top = stack.len() - 1;
+                match (0..run).find(|i| self.builds_on(stack[top - i], d)) {
+                    Some(i) => i + 1,
+                    None => return Err(MoveError::NotBuildable(c, d)),
+                }
+            }
+        };
+        if n > room {
+            return Err(MoveError::TooLong(n));
+        }
+        let mut moves = Vec::new();
+        plan_steps(n, from, to, &free, &cells, &mut moves);
+        Ok(moves)
+    }
+
+    /// Make a move and record it so that it can be undone
+    ///
+    /// The move is not checked against the rules, except that the
+    /// place it moves from must have a card.  Making a move forgets
+    /// the moves that could have been redone.  Returns the card
+    /// moved.
+    pub fn make_move(&mut self, m: Move) -> Card {
+        self.record(m, false)
+    }
+
+    /// Make a move as part of the last move made
+    ///
+    /// A move made up of many moves is undone and redone as a whole
+    /// by looking at [`Board::redo_is_joined`].
+    pub fn make_joined_move(&mut self, m: Move) -> Card {
+        self.record(m, true)
+    }
+
+    fn record(&mut self, m: Move, joined: bool) -> Card {
+        let card = self.last_card(m.source()).expect("no card to move");
+        let foundation = self.foundation_of(card);
+        let prior = self.foundation[foundation];
+        self.step(m);
+        self.done.push(Made {
+            m,
+            card,
+            foundation,
+            prior,
+            joined,
+        });
+        self.undone.clear();
+        card
+    }
+
+    /// Return the moves made that can be undone, first move first
+    pub fn moves_made(&self) -> Vec<Move> {
+        self.done.iter().map(|made| made.m).collect()
+    }
+
+    /// Is the next move to be redone part of the move before it?
+    ///
+    /// Undoing a move made up of many moves continues while this is
+    /// true, and so does redoing one.
+    pub fn redo_is_joined(&self) -> bool {
+        self.undone.last().is_some_and(|made| made.joined)
+    }
+
+    /// Take back the last move made
+    ///
+    /// Returns the move taken back and the card it moved, or None
+    /// when there is nothing to undo.
+    pub fn undo(&mut self) -> Option<(Move, Card)> {
+        let made = self.done.pop()?;
+        match made.m {
+            Move::Stack(from, to) => {
+                let c = self.take_card(to).expect("no card to undo");
+                self.put_card(from, c);
+            }
+            Move::Foundation(from) => {
+                self.foundation_set(made.foundation, made.prior);
+                self.put_card(from, made.card);
+                if self.foundation.iter().all(Option::is_none) {
+                    self.base = self.rules.base;
+                }
+            }
+        }
+        self.undone.push(made);
+        Some((made.m, made.card))
+    }
+
+    /// Make the last move taken back again
+    ///
+    /// Returns the move made and the card it moved, or None when
+    /// there is nothing to redo.
+    pub fn redo(&mut self) -> Option<(Move, Card)> {
+        let made = self.undone.pop()?;
+        self.step(made.m);
+        self.done.push(made);
+        Some((made.m, made.card))
+    }
+
+    // The foundation a card moves to, which is the first of its suit
+    // when the move breaks the rules
+    fn foundation_of(&self, c: Card) -> usize {
+        self.foundation_for(c).unwrap_or(c.suit().index())
+    }
+
+    // Move a card without recording the move
+    fn step(&mut self, m: Move) {
+        let c = self.take_card(m.source()).expect("no card to move");
+        match m {
+            Move::Stack(_, to) => self.put_card(to, c),
+            Move::Foundation(_) => {
+                let f = self.foundation_of(c);
+                self.foundation_set(f, Some(c));
+                self.base.get_or_insert(c.rank());
+            }
+        }
+    }
+
+    /// Create and Shuffle the cards in a board
+    ///
+    /// The cards are shuffled using the deal number as the seed of a
+    /// [`DealRng`], so a deal number always gives the same layout.
+    /// Assumes the board is freshly made or has been cleared.
+    pub fn deal(&mut self, n: u32) {
+        self.deal_with(&mut DealRng::new(n));
+        self.deal = Some(n);
+    }
+
+    /// Create and Shuffle the cards in a board using a given source
+    /// of random numbers
+    ///
+    /// In Beleaguered Castle and Citadel, the aces are placed on the
+    /// foundations and only the other cards are shuffled and dealt.
+    /// In Citadel, a card that can go to its foundation when it is
+    /// dealt is played there, and the next card goes to the stack the
+    /// card would have been placed on.  The board is given no deal
+    /// number.  Assumes the board is freshly made or has been cleared.
+    pub fn deal_with(&mut self, rng: &mut impl Random) {
+        let mut deck: Vec<Card> = (0..self.decks)
+            .flat_map(|_| Card::deck(self.suits, self.ranks))
+            .collect();
+
+        if self.variant.aces_up() {
+            deck.retain(|c| c.rank() != Rank::Ace);
+            for f in 0..self.foundations() {
+                let s = self.foundation_suit(f);
+                self.foundation_set(f, Some(Card::new(Rank::Ace, s)));
+            }
+        }
+
+        shuffle(rng, &mut deck);
+
+        // Deal cards
+        let mode = self.variant.deal_mode();
+        let mut i = 0; // Number of cards placed on stacks
+        for c in deck {
+            match self.foundation_for(c) {
+                Some(f) if mode == DealMode::Citadel => {
+                    self.foundation_set(f, Some(c))
+                }
+                _ => {
+                    self.push_card(i % self.stacks(), c);
+                    i += 1;
+                }
+            }
+        }
+    }
+
+    /// The number of the deal that created this board
+    ///
+    /// Boards restored from a saved game have no deal number.
+    pub fn deal_number(&self) -> Option<u32> {
+        self.deal
+    }
+
+    /// Save a board in the native format used by version 1.3
+    pub fn save(&self, w: &mut impl Write) -> Result<(), SaveError> {
+        self.save_as(w, SaveFormat::default())
+    }
+
+    /// Restore a board saved in the native format used by version 1.3
+    pub fn restore(r: &mut impl Read) -> Result<Board, SaveError> {
+        Board::restore_as(r, SaveFormat::default())
+    }
+
+    /// Save a board using the layout of a saa.sav file
+    ///
+    /// Each number is written as an int in the given format.  First
+    /// comes the magic number, then the number of cards, the
+    /// foundations, and finally each stack as its length followed by
+    /// its cards from bottom to top.  Streets and Alleys games with 4
+    /// suits, 8 stacks, and no reserve cells are saved exactly as
+    /// version 1.3 saves them.  For other games, the magic number is
+    /// [`VARIANT_MAGIC_NUMBER`] and it is followed by the number of
+    /// the variant, the base rank of the foundations, or zero when it
+    /// has yet to be chosen, the number of reserve cells, the number
+    /// of suits, the number of stacks, and the number of decks.  The
+    /// card in each reserve cell, or zero when it is empty, comes
+    /// after the stacks.
+    pub fn save_as(
+        &self,
+        w: &mut impl Write,
+        f: SaveFormat,
+    ) -> Result<(), SaveError> {
+        if self.variant == Variant::StreetsAndAlleys
+            && self.reserves() == 0
+            && self.suits == MAX_SUITS
+            && self.stacks() == DEFAULT_STACKS
+            && self.decks == 1
+        {
+            f.put_int(w, MAGIC_NUMBER as usize)?;
+        } else {
+            f.put_int(w, VARIANT_MAGIC_NUMBER as usize)?;
+            f.put_int(w, self.variant.index())?;
+            f.put_int(w, self.base.map_or(0, Rank::value))?;
+            f.put_int(w, self.reserves())?;
+            f.put_int(w, self.suits)?;
+            f.put_int(w, self.stacks())?;
+            f.put_int(w, self.decks)?;
+        }
+        f.put_int(w, self.foundations() * self.ranks)?;
+        for (i, c) in self.foundation.iter().enumerate() {
+            // An empty foundation is written as the index of its suit.
+            let suit = self.foundation_suit(i).index();
+            f.put_int(w, c.map_or(suit, |c| c.code() as usize))?;
+        }
+        for s in self.stack.iter() {
+            f.put_int(w, s.len())?;
+            for c in s {
+                f.put_int(w, c.code() as usize)?;
+            }
+        }
+        for c in self.reserve.iter() {
+            f.put_int(w, c.map_or(0, |c| c.code() as usize))?;
+        }
+        Ok(())
+    }
+
+    /// Restore a board saved using the layout of a saa.sav file
+    ///
+    /// The board is checked to make sure that each copy of each card
+    /// in the deck is either in a foundation or in exactly one stack
+    /// or reserve cell.
+    pub fn restore_as(
+        r: &mut impl Read,
+        f: SaveFormat,
+    ) -> Result<Board, SaveError> {
+        let magic = f.get_int(r)?;
+        let (variant, base, reserves, suits, stacks, decks) = match magic {
+            n if n == MAGIC_NUMBER as usize => {
+                let v = Variant::StreetsAndAlleys;
+                (v, Some(Rank::Ace), 0, MAX_SUITS, DEFAULT_STACKS, 1)
+            }
+            n if n == VARIANT_MAGIC_NUMBER as usize => {
+                let i = f.get_int(r)?;
+                let variant =
+                    Variant::new(i).ok_or(SaveError::BadVariant(i))?;
+                let n = f.get_int(r)?;
+                let base = match (n, variant.rules().base) {
+                    (0, None) => None,
+                    (n, None) => {
+                        Some(Rank::new(n).ok_or(SaveError::BadBase(n))?)
+                    }
+                    (n, Some(r)) if n == r.value() => Some(r),
+                    (n, _) => return Err(SaveError::BadBase(n)),
+                };
+                let reserves = f.get_int(r)?;
+                if reserves > MAX_RESERVES {
+                    return Err(SaveError::BadReserves(reserves));
+                }
+                let suits = f.get_int(r)?;
+                if suits == 0 || suits > MAX_SUITS {
+                    return Err(SaveError::BadSuits(suits));
+                }
+                let stacks = f.get_int(r)?;
+                if stacks == 0 || stacks > MAX_STACKS {
+                    return Err(SaveError::BadStacks(stacks));
+                }
+                let decks = f.get_int(r)?;
+                if decks == 0 || decks > MAX_DECKS {
+                    return Err(SaveError::BadDecks(decks));
+                }
+                (variant, base, reserves, suits, stacks, decks)
+            }
+            _ => return Err(SaveError::BadMagic),
+        };
+        let cards = f.get_int(r)?;
+        let ranks = cards / (decks * suits);
+        if cards % (decks * suits) != 0 || ranks == 0 || ranks > MAX_RANKS {
+            return Err(SaveError::BadCards(cards));
+        }
+        if base.is_some_and(|r| r.value() > ranks) {
+            return Err(SaveError::BadBase(base.map_or(0, Rank::value)));
+        }
+        let mut b =
+            Board::with_decks(variant, decks, suits, stacks, ranks, reserves);
+        b.base = base;
+        // Copies of cards seen so far indexed by code
+        let mut seen = vec![0; (ranks + 1) * MAX_SUITS];
+        for i in 0..b.foundations() {
+            let suit = b.foundation_suit(i);
+            let code = f.get_int(r)?;
+            let top = if code == suit.index() {
+                None // An empty foundation
+            } else {
+                match Card::from_code(code) {
+                    Some(c)
+                        if c.suit() == suit
+                            && c.rank().value() <= ranks
+                            && base.is_some() =>
+                    {
+                        Some(c)
+                    }
+                    _ => return Err(SaveError::BadFoundation(suit)),
+                }
+            };
+            b.foundation_set(i, top);
+            // Count the cards from the base up to the top card
+            let mut rank = base;
+            for _ in 0..b.foundation_height(i) {
+                let r = rank.expect("no rank on foundation");
+                seen[Card::new(r, suit).code() as usize] += 1;
+                rank = b.next_rank(r);
+            }
+        }
+        for i in 0..stacks {
+            let len = f.get_int(r)?;
+            if len > cards {
+                return Err(SaveError::BadStack(i));
+            }
+            for _ in 0..len {
+                let code = f.get_int(r)?;
+                let c = match Card::from_code(code) {
+                    Some(c) if c.in_deck(suits, ranks) => c,
+                    _ => return Err(SaveError::BadCard(code)),
+                };
+                if seen[code] == decks {
+                    return Err(SaveError::DuplicateCard(c));
+                }
+                seen[code] += 1;
+                b.push_card(i, c);
+            }
+        }
+        for i in 0..reserves {
+            let code = f.get_int(r)?;
+            if code == 0 {
+                continue; // An empty reserve cell
+            }
+            let c = match Card::from_code(code) {
+                Some(c) if c.in_deck(suits, ranks) => c,
+                _ => return Err(SaveError::BadCard(code)),
+            };
+            if seen[code] == decks {
+                return Err(SaveError::DuplicateCard(c));
+            }
+            seen[code] += 1;
+            b.reserve[i] = Some(c);
+        }
+        if let Some(c) =
+            Card::deck(suits, ranks).find(|c| seen[c.code() as usize] < decks)
+        {
+            return Err(SaveError::MissingCard(c));
+        }
+        if r.read(&mut [0])? != 0 {
+            return Err(SaveError::TrailingData);
+        }
+        Ok(b)
+    }
+}
+
+/// A board is written as text with its variant and number of ranks on
+/// the first line, followed by a line for each foundation, each
+/// stack, and each reserve cell.  Each of these lines starts with
+/// the key that selects the place on the screen, 0 for a foundation,
+/// 1 to 9 and then A, B, ... for a stack, and a to d for a reserve
+/// cell.  The cards follow from bottom to top written as on the
+/// screen, as in HT for the ten of hearts.  A foundation lists every
+/// card from its base up, and an empty one is written with its suit
+/// followed by a dash, as in H-.
+///
+/// ```text
+/// streets 5
+/// 0 CA C2
+/// 0 D-
+/// 0 HA
+/// 0 S-
+/// 1 S5 D3 H2
+/// ...
+/// ```
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} {}", self.variant.option_name(), self.ranks)?;
+        for (i, top) in self.foundation.iter().enumerate() {
+            let suit = self.foundation_suit(i);
+            write!(f, "0")?;
+            match (top, self.base) {
+                (Some(top), Some(base)) => {
+                    let mut c = Card::new(base, suit);
+                    write!(f, " {:#}", c)?;
+                    while c != *top {
+                        let r =
+                            self.next_rank(c.rank()).expect("bad foundation");
+                        c = Card::new(r, suit);
+                        write!(f, " {:#}", c)?;
+                    }
+                }
+                _ => write!(f, " {}-", suit.to_char())?,
+            }
+            writeln!(f)?;
+        }
+        for (i, s) in self.stack.iter().enumerate() {
+            write!(f, "{}", stack_label(i))?;
+            for c in s {
+                write!(f, " {:#}", c)?;
+            }
+            writeln!(f)?;
+        }
+        for (i, c) in self.reserve.iter().enumerate() {
+            write!(f, "{}", reserve_name(i))?;
+            if let Some(c) = c {
+                write!(f, " {:#}", c)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// A board is read from the text written for it.  Blank lines are
+/// skipped, cards may be written in either order, as in TH or HT,
+/// and the board is checked to make sure that each copy of each card
+/// in the deck appears exactly once.  The number of suits, stacks,
+/// reserve cells, and decks are given by the lines that describe
+/// them.  The board has no deal number.
+impl FromStr for Board {
+    type Err = ParseBoardError;
+
+    fn from_str(s: &str) -> Result<Board, ParseBoardError> {
+        let at = |line, column, reason| ParseBoardError {
+            line,
+            column,
+            reason,
+        };
+        let end = s.lines().count() + 1; // Errors found at the end
+        let mut lines = (1..)
+            .zip(s.lines().map(words))
+            .filter(|(_, words)| !words.is_empty());
+
+        // The variant and the number of ranks
+        let (n, header) =
+            lines.next().ok_or(at(end, 1, TextError::BadVariant))?;
+        let variant: Variant = header[0]
+            .1
+            .parse()
+            .map_err(|_| at(n, header[0].0, TextError::BadVariant))?;
+        let ranks = match header.get(1).map(|w| (w.0, w.1.parse())) {
+            Some((_, Ok(r))) if (1..=MAX_RANKS).contains(&r) => r,
+            Some((col, _)) => return Err(at(n, col, TextError::BadRanks)),
+            None => return Err(at(n, 1, TextError::BadRanks)),
+        };
+        if let Some((col, _)) = header.get(2) {
+            return Err(at(n, *col, TextError::Extra));
+        }
+
+        // The places and their cards
+        let mut foundations: Vec<(Suit, Vec<Spot>, usize, usize)> = Vec::new();
+        let mut stacks: Vec<Vec<Spot>> = Vec::new();
+        let mut reserves: Vec<Option<Spot>> = Vec::new();
+        for (n, words) in lines {
+            let (col, label) = words[0];
+            let mut cards = words[1..].iter().map(|(col, w)| match w.parse() {
+                Ok(card) => Ok(Spot {
+                    card,
+                    line: n,
+                    column: *col,
+                }),
+                Err(_) => Err(at(n, *col, TextError::BadCard)),
+            });
+            if label == "0" && stacks.is_empty() && reserves.is_empty() {
+                let (col, suit, cards) = match words.get(1) {
+                    Some((col, w)) if w.len() == 2 && w.ends_with('-') => {
+                        let suit = w.chars().next().and_then(Suit::from_char);
+                        match (suit, words.get(2)) {
+                            (Some(suit), None) => (*col, suit, Vec::new()),
+                            (None, _) => {
+                                return Err(at(n, *col, TextError::BadCard))
+                            }
+                            (_, Some((col, _))) => {
+                                return Err(at(n, *col, TextError::Extra))
+                            }
+                        }
+                    }
+                    Some((col, _)) => {
+                        let cards = cards.collect::<Result<Vec<_>, _>>()?;
+                        (*col, cards[0].card.suit(), cards)
+                    }
+                    None => {
+                        return Err(at(n, col, TextError::BadFoundation));
+                    }
+                };
+                foundations.push((suit, cards, n, col));
+            } else if label.len() == 1
+                && label.starts_with(stack_label(stacks.len()))
+                && stacks.len() < MAX_STACKS
+                && reserves.is_empty()
+            {
+                stacks.push(cards.collect::<Result<_, _>>()?);
+            } else if label.len() == 1
+                && label.starts_with(reserve_name(reserves.len()))
+                && reserves.len() < MAX_RESERVES
+            {
+                if let Some((col, _)) = words.get(2) {
+                    return Err(at(n, *col, TextError::FullReserve));
+                }
+                reserves.push(cards.next().transpose()?);
+            } else {
+                return Err(at(n, col, TextError::BadLabel));
+            }
+        }
+        if foundations.is_empty() {
+            return Err(at(end, 1, TextError::NoFoundation));
+        }
+        if stacks.is_empty() {
+            return Err(at(end, 1, TextError::NoStack));
+        }
+
+        // The foundations for the suits of each deck in turn
+        let suits = Suit::ALL
+            .iter()
+            .filter(|s| foundations.iter().any(|f| f.0 == **s))
+            .count();
+        let decks = foundations.len().div_ceil(suits);
+        for (i, (suit, _, n, col)) in foundations.iter().enumerate() {
+            if *suit != Suit::ALL[i % suits] {
+                return Err(at(*n, *col, TextError::FoundationOrder));
+            }
+            if i >= MAX_DECKS * suits {
+                return Err(at(*n, *col, TextError::BadFoundations));
+            }
+        }
+        if !foundations.len().is_multiple_of(suits) {
+            return Err(at(end, 1, TextError::BadFoundations));
+        }
+
+        let mut b = Board::with_decks(
+            variant,
+            decks,
+            suits,
+            stacks.len(),
+            ranks,
+            reserves.len(),
+        );
+
+        // Each card is in the deck and has no more copies than decks
+        let mut seen = vec![0; (ranks + 1) * MAX_SUITS];
+        let spots = foundations
+            .iter()
+            .flat_map(|f| f.1.iter())
+            .chain(stacks.iter().flatten())
+            .chain(reserves.iter().flatten());
+        for spot in spots {
+            let c = spot.card;
+            if !c.in_deck(suits, ranks) {
+                let reason = TextError::NotInDeck(c);
+                return Err(at(spot.line, spot.column, reason));
+            }
+            let code = c.code() as usize;
+            if seen[code] == decks {
+                let reason = TextError::DuplicateCard(c);
+                return Err(at(spot.line, spot.column, reason));
+            }
+            seen[code] += 1;
+        }
+        if let Some(c) =
+            Card::deck(suits, ranks).find(|c| seen[c.code() as usize] < decks)
+        {
+            return Err(at(end, 1, TextError::MissingCard(c)));
+        }
+
+        // Each foundation starts with the base rank and goes up in suit
+        for (i, (suit, cards, _, _)) in foundations.iter().enumerate() {
+            let mut top: Option<Card> = None;
+            for spot in cards {
+                let c = spot.card;
+                if c.suit() != *suit || !b.follows_on_foundation(top, c) {
+                    let reason = TextError::NotNext(c);
+                    return Err(at(spot.line, spot.column, reason));
+                }
+                b.base.get_or_insert(c.rank());
+                top = Some(c);
+            }
+            b.foundation_set(i, top);
+        }
+        for (i, cards) in stacks.iter().enumerate() {
+            for spot in cards {
+                b.push_card(i, spot.card);
+            }
+        }
+        for (i, spot) in reserves.iter().enumerate() {
+            b.reserve[i] = spot.map(|spot| spot.card);
+        }
+        Ok(b)
+    }
+}
+
+// A card read from text and the line and column where it starts
+#[derive(Clone, Copy)]
+struct Spot {
+    card: Card,
+    line: usize,
+    column: usize,
+}
+
+/// The words in a line, each with the column where it starts
+/// counting from one
+pub fn words(line: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut start = None; // Column and index of the current word
+    for (col, (i, ch)) in (1..).zip(line.char_indices()) {
+        match start {
+            None if !ch.is_whitespace() => start = Some((col, i)),
+            Some((c, j)) if ch.is_whitespace() => {
+                words.push((c, &line[j..i]));
+                start = None;
+            }
+            _ => (),
+        }
+    }
+    if let Some((c, j)) = start {
+        words.push((c, &line[j..]));
+    }
+    words
+}
+
+// Stacks are labeled by the keys that select them, 1 to 9 and then
+// capital letters starting with A.
+fn stack_label(s: usize) -> char {
+    let c = std::char::from_digit(s as u32 + 1, 36).expect("no stack label");
+    c.to_ascii_uppercase()
+}
+
+/// The error returned when text does not describe a board
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseBoardError {
+    /// The line of the error counting from one
+    pub line: usize,
+    /// The column of the error counting from one
+    pub column: usize,
+    /// What is wrong
+    pub reason: TextError,
+}
+
+impl fmt::Display for ParseBoardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.reason)
+    }
+}
+
+impl error::Error for ParseBoardError {}
+
+/// The reasons text does not describe a board
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextError {
+    /// The first line does not start with the name of a variant
+    BadVariant,
+    /// The number of ranks is missing or not allowed
+    BadRanks,
+    /// A line has more than it should
+    Extra,
+    /// A line does not start with the label of the next place
+    BadLabel,
+    /// A word is not a card
+    BadCard,
+    /// A foundation has neither cards nor a suit
+    BadFoundation,
+    /// The suits of the foundations are out of order
+    FoundationOrder,
+    /// The foundations do not make up whole decks
+    BadFoundations,
+    /// A reserve cell holds more than one card
+    FullReserve,
+    /// There are no foundations
+    NoFoundation,
+    /// There are no stacks
+    NoStack,
+    /// The card is not in a deck with this many suits and ranks
+    NotInDeck(Card),
+    /// A card appears more often than there are decks
+    DuplicateCard(Card),
+    /// A card of the deck is nowhere to be found
+    MissingCard(Card),
+    /// The card does not follow the card below it in its foundation
+    NotNext(Card),
+}
+
+impl fmt::Display for TextError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TextError::BadVariant => write!(f, "Expected a variant name"),
+            TextError::BadRanks => write!(f, "Bad number of ranks"),
+            TextError::Extra => write!(f, "Extra text"),
+            TextError::BadLabel => write!(f, "Bad place label"),
+            TextError::BadCard => write!(f, "Not a card"),
+            TextError::BadFoundation => write!(f, "Foundation has no suit"),
+            TextError::FoundationOrder => {
+                write!(f, "Foundation suit out of order")
+            }
+            TextError::BadFoundations => {
+                write!(f, "Foundations do not make up whole decks")
+            }
+            TextError::FullReserve => {
+                write!(f, "A reserve cell holds at most one card")
+            }
+            TextError::NoFoundation => write!(f, "No foundations"),
+            TextError::NoStack => write!(f, "No stacks"),
+            TextError::NotInDeck(c) => {
+                write!(f, "The {:#} is not in the deck", c)
+            }
+            TextError::DuplicateCard(c) => {
+                write!(f, "Duplicate card {:#}", c)
+            }
+            TextError::MissingCard(c) => write!(f, "Missing card {:#}", c),
+            TextError::NotNext(c) => {
+                write!(f, "The {:#} does not belong on the foundation", c)
+            }
+        }
+    }
+}
+
+/// The reasons a move is not allowed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MoveError {
+    /// There is no stack with this index
+    NoStack(usize),
+    /// The stack has no card to move
+    NoCard(usize),
+    /// The card does not follow the top card of its foundation
+    NotNext(Card),
+    /// The card may not be built on the top card of the stack (card,
+    /// top card)
+    NotBuildable(Card, Card),
+    /// There are too few empty stacks to move this many cards
+    TooLong(usize),
+    /// The reserve cell has no card to move
+    EmptyReserve(usize),
+    /// The reserve cell already holds a card
+    FullReserve(usize),
+    /// A run cannot be moved from a stack to itself
+    SameStack(usize),
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MoveError::NoStack(s) => write!(f, "There is no stack {}", s + 1),
+            MoveError::NoCard(s) => {
+                write!(f, "There is no card in stack {}", s + 1)
+            }
+            MoveError::NotNext(c) => {
+                write!(f, "The {:#} cannot be moved to the foundation", c)
+            }
+            MoveError::NotBuildable(c, d) => {
+                write!(f, "The {:#} cannot be moved onto the {:#}", c, d)
+            }
+            MoveError::TooLong(n) => {
+                write!(f, "There are too few empty stacks to move {} cards", n)
+            }
+            MoveError::EmptyReserve(i) => {
+                write!(
+                    f,
+                    "There is no card in reserve cell {}",
+                    reserve_name(*i)
+                )
+            }
+            MoveError::FullReserve(i) => {
+                write!(f, "Reserve cell {} is full", reserve_name(*i))
+            }
+            MoveError::SameStack(s) => {
+                write!(
+                    f,
+                    "A run cannot be moved from stack {} to itself",
+                    s + 1
+                )
+            }
+        }
+    }
+}
+
+impl error::Error for MoveError {}
+
+// Reserve cells are named by letters starting with a.
+fn reserve_name(i: usize) -> char {
+    (b'a' + i as u8) as char
+}
+
+// Add the single card moves that move the top n cards of a stack to
+// another stack using the free stacks and cells to hold cards along
+// the way.  When the cells can hold all but one of the cards, they
+// do so while the last card moves.  Otherwise the top half of the
+// cards goes to a free stack, the rest goes to the destination, and
+// then the top half follows it.
+fn plan_steps(
+    n: usize,
+    from: usize,
+    to: usize,
+    free: &[usize],
+    cells: &[usize],
+    moves: &mut Vec<Move>,
+) {
+    if n <= cells.len() + 1 {
+        let cells = &cells[..n - 1];
+        moves.extend(cells.iter().map(|c| Move::Stack(from, *c)));
+        moves.push(Move::Stack(from, to));
+        moves.extend(cells.iter().rev().map(|c| Move::Stack(*c, to)));
+    } else {
+        let (spare, free) = free.split_first().expect("no free stack");
+        let top = n / 2;
+        plan_steps(top, from, *spare, free, cells, moves);
+        plan_steps(n - top, from, to, free, cells, moves);
+        plan_steps(top, *spare, to, free, cells, moves);
+    }
+}
+
+/// Byte order of the ints in a saved game
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endian {
+    /// Least significant byte first
+    Little,
+    /// Most significant byte first
+    Big,
+}
+
+impl Endian {
+    /// The byte order of this machine
+    pub fn native() -> Endian {
+        if cfg!(target_endian = "big") {
+            Endian::Big
+        } else {
+            Endian::Little
+        }
+    }
+}
+
+/// The layout of the ints in a saved game
+///
+/// Version 1.3 wrote each number as a native C int, so a save file
+/// depends on the machine that wrote it.  The default format is the
+/// one used by a C compiler for this machine, a four byte int in
+/// native byte order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SaveFormat {
+    /// Byte order
+    pub endian: Endian,
+    /// Size of an int in bytes (2, 4, or 8)
+    pub int_size: usize,
+}
+
+impl SaveFormat {
+    /// Create a save file format
+    pub fn new(endian: Endian, int_size: usize) -> SaveFormat {
+        match int_size {
+            2 | 4 | 8 => SaveFormat { endian, int_size },
+            _ => panic!("bad int size for a save file format"),
+        }
+    }
+
+    fn put_int(&self, w: &mut impl Write, i: usize) -> Result<(), SaveError> {
+        let max = 1u64 << (8 * self.int_size - 1);
+        if i as u64 >= max {
+            return Err(SaveError::Overflow(i));
+        }
+        let bytes = (i as u64).to_le_bytes();
+        let mut buf = bytes[..self.int_size].to_vec();
+        if self.endian == Endian::Big {
+            buf.reverse();
+        }
+        w.write_all(&buf)?;
+        Ok(())
+    }
+
+    fn get_int(&self, r: &mut impl Read) -> Result<usize, SaveError> {
+        let mut buf = [0; 8];
+        let buf = &mut buf[..self.int_size];
+        r.read_exact(buf).map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => SaveError::Truncated,
+            _ => SaveError::Io(e),
+        })?;
+        if self.endian == Endian::Big {
+            buf.reverse();
+        }
+        // Ints are signed, so a set top bit is a negative number.
+        if buf[self.int_size - 1] & 0x80 != 0 {
+            return Err(SaveError::Negative);
+        }
+        let i = buf.iter().rev().fold(0u64, |i, b| (i << 8) | *b as u64);
+        Ok(i as usize)
+    }
+}
+
+impl Default for SaveFormat {
+    fn default() -> Self {
+        SaveFormat::new(Endian::native(), 4)
+    }
+}
+
+/// The reasons a game cannot be saved or restored
+#[derive(Debug)]
+pub enum SaveError {
+    /// An I/O error
+    Io(io::Error),
+    /// The file ended early
+    Truncated,
+    /// The file does not start with the magic number
+    BadMagic,
+    /// The number of the variant is unknown
+    BadVariant(usize),
+    /// The base rank of the foundations is not allowed
+    BadBase(usize),
+    /// There are too many reserve cells
+    BadReserves(usize),
+    /// The number of suits is not allowed
+    BadSuits(usize),
+    /// The number of stacks is not allowed
+    BadStacks(usize),
+    /// The number of decks is not allowed
+    BadDecks(usize),
+    /// The number of cards does not describe a deck
+    BadCards(usize),
+    /// The foundation for this suit is not a card of the suit
+    BadFoundation(Suit),
+    /// The length of this stack is too large
+    BadStack(usize),
+    /// A stack holds something that is not a card in the deck
+    BadCard(usize),
+    /// A card appears more often than there are decks
+    DuplicateCard(Card),
+    /// A card of the deck is nowhere to be found
+    MissingCard(Card),
+    /// Extra data follows the last stack
+    TrailingData,
+    /// A number is negative
+    Negative,
+    /// A number is too big for the int size
+    Overflow(usize),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::Io(e) => write!(f, "{}", e),
+            SaveError::Truncated => write!(f, "File too short"),
+            SaveError::BadMagic => write!(f, "Bad save file format"),
+            SaveError::BadVariant(n) => write!(f, "Bad variant {}", n),
+            SaveError::BadBase(n) => write!(f, "Bad base rank {}", n),
+            SaveError::BadReserves(n) => {
+                write!(f, "Bad number of reserve cells {}", n)
+            }
+            SaveError::BadSuits(n) => write!(f, "Bad number of suits {}", n),
+            SaveError::BadStacks(n) => {
+                write!(f, "Bad number of stacks {}", n)
+            }
+            SaveError::BadDecks(n) => write!(f, "Bad number of decks {}", n),
+            SaveError::BadCards(n) => write!(f, "Bad number of cards {}", n),
+            SaveError::BadFoundation(i) => {
+                write!(f, "Bad foundation for {}", i.to_char())
+            }
+            SaveError::BadStack(i) => write!(f, "Bad stack {}", i + 1),
+            SaveError::BadCard(c) => write!(f, "Bad card {}", c),
+            SaveError::DuplicateCard(c) => {
+                write!(f, "Duplicate card {:#}", c)
+            }
+            SaveError::MissingCard(c) => {
+                write!(f, "Missing card {:#}", c)
+            }
+            SaveError::TrailingData => write!(f, "Extra data at end of file"),
+            SaveError::Negative => write!(f, "Negative number"),
+            SaveError::Overflow(i) => write!(f, "Number {} too big", i),
+        }
+    }
+}
+
+impl error::Error for SaveError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            SaveError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(e: io::Error) -> Self {
+        SaveError::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Deal many boards from one seeded generator, and count how often
+    // each card lands in each position.  A position is the order in
+    // which it is dealt, so position p is at depth p / stacks of stack
+    // p % stacks.  The counts of each card must pass a chi-square test
+    // for a uniform distribution over the positions.
+    #[test]
+    fn deal_positions_are_uniform() {
+        const DEALS: usize = 5200;
+        let mut rng = DealRng::new(2019);
+        let mut b = Board::new(Variant::StreetsAndAlleys, 4, 8, 13);
+        let cards = 52;
+        let mut counts = vec![vec![0usize; cards]; 14 * MAX_SUITS];
+        for _ in 0..DEALS {
+            b.clear();
+            b.deal_with(&mut rng);
+            for s in 0..b.stacks() {
+                for (j, c) in b.stack_iter(s).enumerate() {
+                    counts[c.code() as usize][j * b.stacks() + s] += 1;
+                }
+            }
+        }
+        let expected = (DEALS / cards) as f64;
+        for c in Card::deck(4, 13) {
+            let chi2: f64 = counts[c.code() as usize]
+                .iter()
+                .map(|&n| (n as f64 - expected).powi(2) / expected)
+                .sum();
+            // With 51 degrees of freedom, a uniform shuffle exceeds
+            // 100 less than once in 10,000 cards.
+            assert!(chi2 < 100.0, "{} has chi-square {:.1}", c, chi2);
+        }
+    }
+
+    // A deal number must give the same layout in every version.
+    #[test]
+    fn deal_number_gives_fixed_layout() {
+        let mut b = Board::new(Variant::StreetsAndAlleys, 4, 8, 13);
+        b.deal(1);
+        let layout = "\
+streets 13
+0 C-
+0 D-
+0 H-
+0 S-
+1 CT H4 D2 H5 C7 HK D5
+2 DT S5 SJ S6 C6 HJ DJ
+3 C5 CK CQ S8 H3 HA C8
+4 D7 SQ H9 H8 S9 D8 S2
+5 H2 SK S4 ST C4 D9
+6 CJ D6 HQ DA S3 D3
+7 H7 SA C3 D4 C2 DK
+8 C9 HT DQ S7 CA H6
+";
+        assert_eq!(b.to_string(), layout);
+        assert_eq!(b.deal_number(), Some(1));
+    }
+
+    // A saved game must not bring in a card of a suit not in play.
+    #[test]
+    fn restore_refuses_card_of_unused_suit() {
+        let mut b = Board::new(Variant::StreetsAndAlleys, 2, 8, 13);
+        b.deal(1);
+        let h2 = Card::new(Rank::Two, Suit::Hearts);
+        b.push_card(7, h2);
+        let mut buf = Vec::new();
+        b.save(&mut buf).unwrap();
+        match Board::restore(&mut &buf[..]) {
+            Err(SaveError::BadCard(code)) => {
+                assert_eq!(code, h2.code() as usize)
+            }
+            Err(e) => panic!("wrong error: {}", e),
+            Ok(_) => panic!("restored a board with the H2"),
+        }
+    }
+
+    // In Chessboard the last card of a suit may be built on its base
+    // card, so moving the base card to an empty foundation can hurt.
+    #[test]
+    fn wrapped_base_card_is_not_safe() {
+        let b: Board = "\
+chessboard 5
+0 C3
+0 D-
+1 C4 D3
+2 D2 CA
+3 C5 D5
+4 DA C2 D4
+"
+        .parse()
+        .unwrap_or_else(|e| panic!("{}", e));
+        assert!(b.is_legal(Move::Foundation(0)));
+        assert_eq!(b.safe_move(), None);
+    }
+
+    // In Fortress the card below the top of a stack may build on the
+    // top card, but a run must not be planned from a stack to itself.
+    #[test]
+    fn run_from_stack_to_itself_is_refused() {
+        let b: Board = "\
+fortress 5
+0 C-
+0 D-
+1 C5 C2 C3
+2 CA D5 D4
+3 C4 DA
+4 D2 D3
+"
+        .parse()
+        .unwrap_or_else(|e| panic!("{}", e));
+        for s in 0..b.stacks() {
+            match b.plan_run(s, s) {
+                Err(MoveError::SameStack(t)) => assert_eq!(s, t),
+                r => panic!("stack {} planned {:?}", s + 1, r),
+            }
+        }
+    }
+
+    // The ints of a version 1.3 save file for the usual game with
+    // empty foundations.  Stack s holds the cards with codes 4 + s,
+    // 12 + s, and so on, so stack 1 starts with the ace of clubs.
+    fn classic_ints() -> Vec<i32> {
+        let mut ints = vec![MAGIC_NUMBER, 52, 0, 1, 2, 3];
+        for s in 0..8 {
+            let codes: Vec<i32> = (4 + s..56).step_by(8).collect();
+            ints.push(codes.len() as i32);
+            ints.extend(codes);
+        }
+        ints
+    }
+
+    // Write ints the way a C build on a little-endian machine with
+    // four byte ints does
+    fn c_file(ints: &[i32]) -> Vec<u8> {
+        ints.iter().flat_map(|i| i.to_le_bytes()).collect()
+    }
+
+    fn restore_c(ints: &[i32]) -> Result<Board, SaveError> {
+        let f = SaveFormat::new(Endian::Little, 4);
+        Board::restore_as(&mut &c_file(ints)[..], f)
+    }
+
+    #[test]
+    fn restore_file_written_by_c() {
+        let b = restore_c(&classic_ints()).unwrap();
+        assert_eq!(b.variant(), Variant::StreetsAndAlleys);
+        assert_eq!((b.suits(), b.stacks(), b.ranks()), (4, 8, 13));
+        assert_eq!(b.deal_number(), None);
+        let first: Vec<String> = b
+            .stack_iter(0)
+            .take(3)
+            .map(|c| format!("{:#}", c))
+            .collect();
+        assert_eq!(first, ["CA", "C3", "C5"]);
+        assert_eq!(b.stack_len(0), 7);
+        assert_eq!(b.stack_len(7), 6);
+        for f in 0..4 {
+            assert_eq!(b.foundation_ref(f), None);
+        }
+    }
+
+    #[test]
+    fn save_round_trip_in_big_endian() {
+        let mut b = Board::with_reserves(Variant::Fortress, 3, 6, 9, 2);
+        b.deal(17);
+        let m = b.legal_moves()[0];
+        b.apply(m).unwrap();
+        for &size in &[2, 8] {
+            let f = SaveFormat::new(Endian::Big, size);
+            let mut buf = Vec::new();
+            b.save_as(&mut buf, f).unwrap();
+            assert_eq!(buf[size - 1] as i32, VARIANT_MAGIC_NUMBER & 0xff);
+            let r = Board::restore_as(&mut &buf[..], f).unwrap();
+            assert_eq!(r.to_string(), b.to_string());
+        }
+    }
+
+    #[test]
+    fn restore_errors() {
+        let ints = classic_ints();
+        let file = c_file(&ints);
+        let f = SaveFormat::new(Endian::Little, 4);
+        let short = &file[..file.len() - 2];
+        assert!(matches!(
+            Board::restore_as(&mut &short[..], f),
+            Err(SaveError::Truncated)
+        ));
+        let mut bad = ints.clone();
+        bad[0] = 12345;
+        assert!(matches!(restore_c(&bad), Err(SaveError::BadMagic)));
+        let mut long = ints.clone();
+        long.push(0);
+        assert!(matches!(restore_c(&long), Err(SaveError::TrailingData)));
+        // The length of the first stack is negative.
+        let mut neg = ints.clone();
+        neg[6] = -1;
+        assert!(matches!(restore_c(&neg), Err(SaveError::Negative)));
+        // The second card of stack 1 is replaced by its first card.
+        let mut dup = ints.clone();
+        dup[8] = dup[7];
+        let ca = Card::new(Rank::Ace, Suit::Clubs);
+        match restore_c(&dup) {
+            Err(SaveError::DuplicateCard(c)) => assert_eq!(c, ca),
+            r => panic!("expected a duplicate card, got {:?}", r.err()),
+        }
+        // The last card of stack 8 is dropped.
+        let mut missing = ints.clone();
+        let last = missing.pop().unwrap();
+        let at = missing.len() - 6;
+        missing[at] -= 1;
+        match restore_c(&missing) {
+            Err(SaveError::MissingCard(c)) => {
+                assert_eq!(c.code() as i32, last)
+            }
+            r => panic!("expected a missing card, got {:?}", r.err()),
+        }
+        let f = SaveFormat::new(Endian::Big, 2);
+        let mut buf = Vec::new();
+        assert!(matches!(
+            f.put_int(&mut buf, 40_000),
+            Err(SaveError::Overflow(40_000))
+        ));
+    }
+
+    fn card(s: &str) -> Card {
+        s.parse().unwrap()
+    }
+
+    // A Streets and Alleys board with a reserve cell holding the S2
+    // and an empty stack 4
+    fn stronghold() -> Board {
+        "\
+stronghold 5
+0 CA
+0 D-
+0 H-
+0 S-
+1 D5 C2
+2 H3 S4
+3 C5 D3
+4
+5 H5 DA H2
+6 S3 C3 SA
+7 D2 S5 HA
+8 D4 H4 C4
+a S2
+"
+        .parse()
+        .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    #[test]
+    fn check_moves_between_stacks() {
+        let b = stronghold();
+        assert_eq!(b.check(Move::Stack(6, 0)), Ok(card("HA")));
+        assert_eq!(
+            b.check(Move::Stack(1, 0)),
+            Err(MoveError::NotBuildable(card("S4"), card("C2")))
+        );
+        assert_eq!(b.check(Move::Stack(1, 3)), Ok(card("S4")));
+        assert_eq!(b.check(Move::Stack(3, 0)), Err(MoveError::NoCard(3)));
+        assert_eq!(b.check(Move::Stack(0, 9)), Err(MoveError::NoStack(9)));
+    }
+
+    #[test]
+    fn check_moves_to_foundations() {
+        let b = stronghold();
+        assert_eq!(b.check(Move::Foundation(0)), Ok(card("C2")));
+        assert_eq!(b.check(Move::Foundation(6)), Ok(card("HA")));
+        assert_eq!(
+            b.check(Move::Foundation(2)),
+            Err(MoveError::NotNext(card("D3")))
+        );
+        assert_eq!(b.check(Move::Foundation(3)), Err(MoveError::NoCard(3)));
+    }
+
+    #[test]
+    fn check_moves_with_reserve_cells() {
+        let mut b = stronghold();
+        assert_eq!(b.check(Move::Stack(0, 8)), Err(MoveError::FullReserve(0)));
+        assert_eq!(b.apply(Move::Stack(8, 3)), Ok(card("S2")));
+        assert_eq!(b.check(Move::Stack(8, 0)), Err(MoveError::EmptyReserve(0)));
+        assert_eq!(
+            b.check(Move::Foundation(8)),
+            Err(MoveError::EmptyReserve(0))
+        );
+        assert_eq!(b.apply(Move::Stack(0, 8)), Ok(card("C2")));
+        assert_eq!(b.check(Move::Foundation(8)), Ok(card("C2")));
+    }
+
+    #[test]
+    fn legal_moves_list_foundation_moves_first() {
+        let b = stronghold();
+        let moves = b.legal_moves();
+        let n = moves
+            .iter()
+            .take_while(|m| matches!(m, Move::Foundation(_)))
+            .count();
+        assert_eq!(
+            moves[..n],
+            [
+                Move::Foundation(0),
+                Move::Foundation(5),
+                Move::Foundation(6)
+            ]
+        );
+        assert!(moves[n..].iter().all(|m| matches!(m, Move::Stack(..))));
+        assert!(moves[n..].contains(&Move::Stack(6, 0)));
+        assert!(!moves.contains(&Move::Stack(1, 0)));
+        assert!(moves.iter().all(|m| b.is_legal(*m)));
+    }
+
+    // The line, column, and reason of the error parsing text
+    fn text_error(text: &str) -> (usize, usize, TextError) {
+        match text.parse::<Board>() {
+            Err(e) => (e.line, e.column, e.reason),
+            Ok(b) => panic!("parsed a board:\n{}", b),
+        }
+    }
+
+    #[test]
+    fn text_errors_give_line_and_column() {
+        let cases = [
+            // A stack label out of turn, indented
+            (
+                "streets 5\n0 C-\n1 C2 C3\n  3 C4 C5 CA\n",
+                4,
+                3,
+                TextError::BadLabel,
+            ),
+            // A blank line still counts
+            (
+                "streets 5\n0 C-\n\n1 C2 C3 C3\n2 C4 C5 CA\n",
+                4,
+                9,
+                TextError::DuplicateCard(card("C3")),
+            ),
+            // Missing cards are found at the end
+            (
+                "streets 5\n0 C-\n1 C2 C3\n2 C4 C5\n",
+                5,
+                1,
+                TextError::MissingCard(card("CA")),
+            ),
+            (
+                "streets 5\n0 C-\n1 C2 C3 C6\n2 C4 C5 CA\n",
+                3,
+                9,
+                TextError::NotInDeck(card("C6")),
+            ),
+            (
+                "streets 5\n0 C-\n1 C2 C3 D2\n2 C4 C5 CA\n",
+                3,
+                9,
+                TextError::NotInDeck(card("D2")),
+            ),
+            (
+                "streets 5\n0 CA C3\n1 C2\n2 C4 C5\n",
+                2,
+                6,
+                TextError::NotNext(card("C3")),
+            ),
+            (
+                "streets 5\n0 H-\n0 C-\n1 C2 C3 CA\n",
+                2,
+                3,
+                TextError::FoundationOrder,
+            ),
+            (
+                "streets 5\n0 C-\n1 C2 C3\n2 C4\na CA C5\n",
+                5,
+                6,
+                TextError::FullReserve,
+            ),
+        ];
+        for (text, line, column, reason) in cases.iter() {
+            assert_eq!(text_error(text), (*line, *column, *reason), "{}", text);
+        }
+    }
+
+    // The text written for a board of each variant reads back as the
+    // same board.
+    #[test]
+    fn text_round_trip_for_each_variant() {
+        for (i, v) in Variant::ALL.iter().enumerate() {
+            let mut b =
+                Board::with_decks(*v, 1, 4, v.stacks(), 13, v.reserves());
+            b.deal(i as u32 + 1);
+            let text = b.to_string();
+            let read: Board = text.parse().unwrap_or_else(|e| panic!("{}", e));
+            assert_eq!(read.to_string(), text);
+            assert_eq!(read.variant(), *v);
+            assert_eq!(read.stacks(), v.stacks());
+            assert_eq!(read.reserves(), v.reserves());
         }
     }
 }
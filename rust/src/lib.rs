@@ -12,19 +12,46 @@
 // implied warranty.
 
 pub mod board;
+pub mod record;
 pub mod screen;
+pub mod solver;
 pub mod window;
 
 use screen::Screen;
 use window::Window;
 
-/// Run the game given an initial rank and a version string
-pub fn run(nranks: usize, version: &'static str) {
+/// The settings gleaned from command line argument processing
+pub struct Options {
+    /// The game to be played
+    pub variant: board::Variant,
+    /// Number of decks shuffled together in the first game
+    pub decks: usize,
+    /// Number of suits used in the first game
+    pub suits: usize,
+    /// Number of stacks used in the first game
+    pub stacks: usize,
+    /// Number of ranks used in the first game
+    pub ranks: usize,
+    /// Number of reserve cells to use instead of the variant's
+    pub reserves: Option<usize>,
+    /// Number of the first deal, if one was requested
+    pub deal: Option<u32>,
+    /// Board on which the first game starts instead of a deal
+    pub board: Option<board::Board>,
+    /// Recorded game viewed before play starts
+    pub record: Option<record::Record>,
+    /// Move cards to the foundations when it is safe to do so
+    pub autoplay: bool,
+    /// Draw suits as Unicode glyphs when the locale allows it
+    pub glyphs: bool,
+}
+
+/// Run the game given the options and a version string
+pub fn run(opts: &Options, version: &'static str) {
     let w = Window::new();
     Window::cbreak();
     Window::noecho();
 
-    Screen::new(w, version).play(nranks);
-
-    Window::endwin();
+    // The window restores the terminal when it is dropped.
+    Screen::new(w, version).play(opts);
 }
@@ -11,28 +11,137 @@
 extern crate saa;
 
 use std::env;
+use std::fmt;
+use std::fs;
+use std::process;
+use std::str::FromStr;
 
 const DEFAULT_RANKS: usize = 11;
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let mut args = env::args().peekable();
+    let prog = args.next().unwrap_or_else(|| "saa".to_string());
+    if args.peek().is_some_and(|arg| arg == "replay") {
+        args.next();
+        return match (args.next(), args.next()) {
+            (Some(path), None) => replay(&prog, &path),
+            _ => usage(&prog),
+        };
+    }
+    let mut variant = saa::board::Variant::default();
+    let mut ranks = None;
+    let mut decks = 1;
+    let mut suits = saa::board::MAX_SUITS;
+    let mut stacks = None;
+    let mut reserves = None;
+    let mut deal = None;
+    let mut board = None;
+    let mut record = None;
+    let mut autoplay = false;
+    let mut glyphs = false;
 
-    if args.len() > 2 {
-        usage(&args[0]);
-    } else if args.len() == 1 {
-        saa::run(DEFAULT_RANKS, VERSION);
-    } else {
-        // args.len() == 2
-        match usize::from_str_radix(&args[1], 10) {
-            Ok(ranks) => {
-                if ranks >= 5 && ranks <= 13 {
-                    saa::run(ranks, VERSION)
-                } else {
-                    usage(&args[0])
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => match args.next().and_then(|s| s.parse().ok()) {
+                Some(n) => deal = Some(n),
+                None => return usage(&prog),
+            },
+            "--variant" => match args.next().and_then(|s| s.parse().ok()) {
+                Some(v) => variant = v,
+                None => return usage(&prog),
+            },
+            "--reserves" => match args.next().and_then(|s| s.parse().ok()) {
+                Some(n) if n <= saa::board::MAX_RESERVES => reserves = Some(n),
+                _ => return usage(&prog),
+            },
+            "--decks" => match args.next().and_then(|s| s.parse().ok()) {
+                Some(n) if (1..=saa::board::MAX_DECKS).contains(&n) => {
+                    decks = n
+                }
+                _ => return usage(&prog),
+            },
+            "--suits" => match args.next().and_then(|s| s.parse().ok()) {
+                Some(n) if (1..=saa::board::MAX_SUITS).contains(&n) => {
+                    suits = n
+                }
+                _ => return usage(&prog),
+            },
+            "--stacks" => match args.next().and_then(|s| s.parse().ok()) {
+                Some(n) if (1..=saa::board::MAX_STACKS).contains(&n) => {
+                    stacks = Some(n)
                 }
+                _ => return usage(&prog),
+            },
+            "--board" => match args.next() {
+                Some(path) => board = Some(read_or_exit(&prog, &path)),
+                None => return usage(&prog),
+            },
+            "--view" => match args.next() {
+                Some(path) => record = Some(read_or_exit(&prog, &path)),
+                None => return usage(&prog),
+            },
+            "--autoplay" => autoplay = true,
+            "--glyphs" => glyphs = true,
+            _ if ranks.is_none() => match arg.parse::<usize>() {
+                Ok(n) if (5..=13).contains(&n) => ranks = Some(n),
+                _ => return usage(&prog),
+            },
+            _ => return usage(&prog),
+        }
+    }
+    let opts = saa::Options {
+        variant,
+        decks,
+        suits,
+        stacks: stacks.unwrap_or(variant.stacks()),
+        ranks: ranks.unwrap_or(DEFAULT_RANKS),
+        reserves,
+        deal,
+        board,
+        record,
+        autoplay,
+        glyphs,
+    };
+    saa::run(&opts, VERSION);
+}
+
+// Read a board or a record from a file written as text, and exit
+// describing the error starting with the file name if it cannot be
+// read.
+fn read_or_exit<T>(prog: &str, path: &str) -> T
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    match fs::read_to_string(path) {
+        Ok(text) => match text.parse() {
+            Ok(t) => t,
+            Err(e) => {
+                eprintln!("{}: {}:{}", prog, path, e);
+                process::exit(1);
             }
-            Err(_) => usage(&args[0]),
+        },
+        Err(e) => {
+            eprintln!("{}: {}: {}", prog, path, e);
+            process::exit(1);
+        }
+    }
+}
+
+// Replay the game in a record file checking that each move is legal
+fn replay(prog: &str, path: &str) {
+    let record: saa::record::Record = read_or_exit(prog, path);
+    match record.replay() {
+        Ok(_) => println!(
+            "{}: {} moves replayed, all legal, and the game was {}.",
+            path,
+            record.moves().len(),
+            record.finish().name()
+        ),
+        Err(e) => {
+            eprintln!("{}: {}: {}.", prog, path, e);
+            process::exit(1);
         }
     }
 }
@@ -41,28 +150,87 @@ fn usage(prog: &str) {
     println!(
         "       Streets and Alleys version {}
 
-There are eight stacks of cards and a foundation for each suit.  A
-card may be moved from the top of a stack to its foundation or to
-the top of another stack.  The object of the game is to order the
-cards in each stack so that each card is covered only by cards of
-lesser rank. The ace has the smallest rank and the king has the
-greatest rank.
+There are eight stacks of cards in the usual game, and a foundation
+for each suit.  A card may be moved from the top of a stack to its
+foundation or to the top of another stack.  The object of the game
+is to order the cards in each stack so that each card is covered
+only by cards of lesser rank. The ace has the smallest rank and the
+king has the greatest rank.
 
 A card may be moved to its foundation when the card's predecessor of
 the same suit is there.  A card may be moved to a stack when the top
 card of the stack has rank one greater than the card being moved.  A
-card can always be moved to an empty stack.
+card can always be moved to an empty stack or an empty reserve cell.
 
 Commands:                              Command Aliases:
-
-  0    Select a foundation.              <space> = 0,
-  1-8  Select a stack.                   j = 1, k = 2, l = 3, ; = 4,
+  0    Select a foundation.              <space> = 0, [ = 9, ] = A,
+  1-9  Select a stack, then A, B, ...    j = 1, k = 2, l = 3, ; = 4,
   q    Quit the game.                    u = 5, i = 6, o = 7, p = 8.
-  ?    Print this help and then refresh screen.
+  r    Restore a game from a file.       z, y Undo or redo a move.
+  s    Save a game in a file.            t    Toggle automatic play.
+  h    Suggest a move.                   ?    Print this help.
+  m    Move a run of cards.              w    Write a record of the game.
+  a-d  Select a reserve cell.            <-, -> Move cursor, enter selects.
 
-Usage: {} [number_of_ranks].
+Usage: {} [--variant name] [--seed deal_number] [--autoplay] [--glyphs]
+           [--reserves number_of_cells] [--suits number_of_suits]
+           [--stacks number_of_stacks] [--decks number_of_decks]
+           [--board file_name] [--view record_file] [number_of_ranks].
+       {} replay record_file
 The number of ranks must be between 5 and 13.
-The default number of ranks is {}.",
-        VERSION, prog, DEFAULT_RANKS
+The default number of ranks is {}.
+A deal number gives the same layout every time it is used.  The
+number of the current deal is shown in the title.
+The --autoplay option moves cards to the foundations whenever doing
+so cannot hurt.  The --glyphs option draws suits as the symbols
+♣, ♦, ♥, and ♠ when the locale uses UTF-8, and as C, D, H, and S
+otherwise.
+The variant is streets for Streets and Alleys, the default, castle
+for Beleaguered Castle, in which the aces start on the foundations,
+citadel for Citadel, in which cards are also played to the
+foundations as they are dealt, fortress for Fortress, in which cards
+are built up or down in suit, chessboard for Chessboard, which is
+Fortress with ranks that wrap around and a foundation base rank set
+by the first card moved to a foundation, stronghold for Stronghold,
+which is Streets and Alleys with a reserve cell, or fortress-reserve
+for Fortress with a reserve cell.
+The --reserves option gives a game up to {} reserve cells, each of
+which holds one card.  Extra reserve cells make for easier practice.
+The number of suits may be between 1 and {}, and the number of stacks
+between 1 and {}.  The usual game has {} suits and {} stacks, and
+Fortress and Chessboard are dealt into {}.  Stacks after the ninth
+are selected with capital letters starting with A.
+The --decks option shuffles up to {} decks together.  Each suit then
+has a foundation for each deck, and either copy of a card may be
+moved to a foundation of its suit.
+The --board option starts the first game on the board in a file
+written as text.  The first line gives the variant and the number of
+ranks, as in streets 13.  Each other line starts with the key that
+selects a foundation, a stack, or a reserve cell, and lists its cards
+from bottom to top, as in 1 HT C4 SK.  An empty foundation is written
+with its suit, as in 0 H-.
+The w command writes a record of the game in saa.rec, and the record
+of each game is written there when it ends.  The replay command plays
+the moves in a record again and checks that each one is legal.  The
+--view option shows a recorded game one move at a time: n or space
+makes the next move, p takes one back, < and > go to the start and
+the end, g goes to a move by number, l starts playing from the move
+shown, and q quits.
+The left and right arrow keys move a cursor across the foundation
+column, the reserve cells, and the stacks.  Enter or space selects
+the place under the cursor, so a card is moved by selecting the
+place it is in and then the place it goes to.  Typing the key of a
+place hides the cursor, and space again selects a foundation.",
+        VERSION,
+        prog,
+        prog,
+        DEFAULT_RANKS,
+        saa::board::MAX_RESERVES,
+        saa::board::MAX_SUITS,
+        saa::board::MAX_STACKS,
+        saa::board::MAX_SUITS,
+        saa::board::DEFAULT_STACKS,
+        saa::board::Variant::Fortress.stacks(),
+        saa::board::MAX_DECKS
     );
 }
@@ -0,0 +1,518 @@
+//! # Streets and Alleys game records
+
+//! This module describes the record of a game, which holds the board
+//! on which the game started, the moves made, and how the game ended.
+//! A record is written as text so that a game can be shared and
+//! replayed, and replaying a record checks that each of its moves is
+//! allowed by the rules.
+
+// Copyright (c) 2019 John D. Ramsdell
+// Permission to use, copy, modify, and distribute this software and its
+// documentation for any purpose and without fee is hereby granted,
+// provided that the above copyright notice appear in all copies.  John
+// Ramsdell makes no representations about the suitability of this
+// software for any purpose.  It is provided "as is" without express or
+// implied warranty.
+
+use super::board::*;
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+// Moves written on each line of a record
+const MOVES_PER_LINE: usize = 10;
+
+/// How a game ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Finish {
+    /// Every card can be moved to its foundation
+    Won,
+    /// The player gave up
+    Lost,
+    /// The game is still being played
+    Unfinished,
+}
+
+impl Finish {
+    /// The word used for the finish in a record
+    pub fn name(self) -> &'static str {
+        match self {
+            Finish::Won => "won",
+            Finish::Lost => "lost",
+            Finish::Unfinished => "unfinished",
+        }
+    }
+}
+
+/// The record of a game
+#[derive(Clone)]
+pub struct Record {
+    start: Board,
+    moves: Vec<Move>,
+    finish: Finish,
+}
+
+impl Record {
+    /// Make the record of the game played on a board
+    ///
+    /// The game started on the board as it was before the moves that
+    /// can be undone, and its moves are those moves.
+    pub fn new(b: &Board, finish: Finish) -> Record {
+        let mut start = b.clone();
+        while start.undo().is_some() {}
+        Record {
+            start,
+            moves: b.moves_made(),
+            finish,
+        }
+    }
+
+    /// Return the board on which the game started
+    pub fn start(&self) -> &Board {
+        &self.start
+    }
+
+    /// Return the moves of the game
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    /// Return how the game ended
+    pub fn finish(&self) -> Finish {
+        self.finish
+    }
+
+    /// Play the moves of the game again
+    ///
+    /// Each move is checked against the rules, and the game must be
+    /// done exactly when it was won.  Returns the board after the
+    /// last move.
+    pub fn replay(&self) -> Result<Board, ReplayError> {
+        let mut b = self.start.clone();
+        for (i, m) in self.moves.iter().enumerate() {
+            if let Err(reason) = b.apply(*m) {
+                return Err(ReplayError::Illegal {
+                    number: i + 1,
+                    name: move_name(&b, *m),
+                    reason,
+                });
+            }
+        }
+        if b.is_done() != (self.finish == Finish::Won) {
+            return Err(ReplayError::WrongFinish(self.finish));
+        }
+        Ok(b)
+    }
+}
+
+// A move is written as the keys that select the place it moves from
+// and the place it moves to, as in 3-0 or 5-2.
+fn move_name(b: &Board, m: Move) -> String {
+    let from = b.place_label(m.source());
+    match m {
+        Move::Stack(_, to) => format!("{}-{}", from, b.place_label(to)),
+        Move::Foundation(_) => format!("{}-0", from),
+    }
+}
+
+// The move written as text, if any
+fn parse_move(b: &Board, s: &str) -> Option<Move> {
+    let mut chars = s.chars();
+    let from = b.label_place(chars.next()?)?;
+    if chars.next()? != '-' {
+        return None;
+    }
+    let m = match chars.next()? {
+        '0' => Move::Foundation(from),
+        c => Move::Stack(from, b.label_place(c)?),
+    };
+    match chars.next() {
+        None => Some(m),
+        Some(_) => None,
+    }
+}
+
+/// A record is written one item to a line, each line starting with a
+/// keyword.  The variant and the number of ranks come first.  A game
+/// that started with a deal gives the deal number followed by the
+/// numbers of suits, stacks, reserve cells, and decks.  Otherwise the
+/// keyword layout is followed by the lines that describe the board
+/// after its first line, as in the text written for a [`Board`].
+/// Next come the moves, as in 3-0 or 5-2, on lines that start with
+/// the keyword moves, and last the result, which is won, lost, or
+/// unfinished.
+///
+/// ```text
+/// variant streets
+/// ranks 13
+/// deal 5
+/// suits 4
+/// stacks 8
+/// reserves 0
+/// decks 1
+/// moves 3-0 5-2 1-5
+/// result unfinished
+/// ```
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let b = &self.start;
+        writeln!(f, "variant {}", b.variant().option_name())?;
+        writeln!(f, "ranks {}", b.ranks())?;
+        match b.deal_number() {
+            Some(n) => {
+                writeln!(f, "deal {}", n)?;
+                writeln!(f, "suits {}", b.suits())?;
+                writeln!(f, "stacks {}", b.stacks())?;
+                writeln!(f, "reserves {}", b.reserves())?;
+                writeln!(f, "decks {}", b.decks())?;
+            }
+            None => {
+                writeln!(f, "layout")?;
+                // Skip the line with the variant and ranks
+                for line in b.to_string().lines().skip(1) {
+                    writeln!(f, "{}", line)?;
+                }
+            }
+        }
+        for line in self.moves.chunks(MOVES_PER_LINE) {
+            write!(f, "moves")?;
+            for m in line {
+                write!(f, " {}", move_name(b, *m))?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f, "result {}", self.finish.name())
+    }
+}
+
+// The settings read from a record before its board is made
+struct Settings {
+    variant: Option<Variant>,
+    ranks: Option<usize>,
+    deal: Option<u32>,
+    suits: usize,
+    stacks: Option<usize>,
+    reserves: Option<usize>,
+    decks: usize,
+    layout: Option<String>, // Board text with lines kept in place
+    moves: Vec<(usize, usize, String)>, // Line, column, and move
+    finish: Option<Finish>,
+}
+
+/// A record is read from the text written for it.  Blank lines are
+/// skipped.  The record is checked to make sure it describes a board
+/// and that each move names places on the board, but the moves are
+/// not checked against the rules until the record is replayed.
+impl FromStr for Record {
+    type Err = ParseRecordError;
+
+    fn from_str(s: &str) -> Result<Record, ParseRecordError> {
+        let at = |line, column, reason| ParseRecordError {
+            line,
+            column,
+            reason,
+        };
+        let end = s.lines().count() + 1; // Errors found at the end
+        let mut set = Settings {
+            variant: None,
+            ranks: None,
+            deal: None,
+            suits: MAX_SUITS,
+            stacks: None,
+            reserves: None,
+            decks: 1,
+            layout: None,
+            moves: Vec::new(),
+            finish: None,
+        };
+        for (n, line) in (1..).zip(s.lines()) {
+            let words = words(line);
+            let (col, key) = match words.first() {
+                Some(w) => *w,
+                None => continue,
+            };
+            // A layout line starts with the key of a place.
+            if let Some(text) = set.layout.as_mut() {
+                if key.chars().count() == 1 {
+                    let lines = text.lines().count();
+                    text.push_str(&"\n".repeat(n - lines));
+                    text.push_str(line);
+                    continue;
+                }
+            }
+            if key == "moves" {
+                for (col, w) in &words[1..] {
+                    set.moves.push((n, *col, w.to_string()));
+                }
+                continue;
+            }
+            if key == "layout" {
+                if let Some((col, _)) = words.get(1) {
+                    return Err(at(n, *col, RecordError::Extra));
+                }
+                // The first line of the board replaces this one.
+                let (v, r) = match (set.variant, set.ranks) {
+                    (Some(v), Some(r)) => (v, r),
+                    _ => return Err(at(n, col, RecordError::EarlyLayout)),
+                };
+                let mut text = "\n".repeat(n - 1);
+                text.push_str(&format!("{} {}", v.option_name(), r));
+                set.layout = Some(text);
+                continue;
+            }
+            // Every other keyword has one value.
+            let (vcol, value) = match words.get(1) {
+                Some(w) => *w,
+                None => return Err(at(n, col, RecordError::NoValue)),
+            };
+            if let Some((col, _)) = words.get(2) {
+                return Err(at(n, *col, RecordError::Extra));
+            }
+            let bad = at(n, vcol, RecordError::BadValue);
+            let number = |range: std::ops::RangeInclusive<usize>| {
+                value.parse().ok().filter(|n| range.contains(n)).ok_or(bad)
+            };
+            match key {
+                "variant" => {
+                    set.variant = Some(value.parse().map_err(|_| bad)?)
+                }
+                "ranks" => set.ranks = Some(number(1..=MAX_RANKS)?),
+                "deal" => set.deal = Some(value.parse().map_err(|_| bad)?),
+                "suits" => set.suits = number(1..=MAX_SUITS)?,
+                "stacks" => set.stacks = Some(number(1..=MAX_STACKS)?),
+                "reserves" => set.reserves = Some(number(0..=MAX_RESERVES)?),
+                "decks" => set.decks = number(1..=MAX_DECKS)?,
+                "result" => {
+                    set.finish = Some(match value {
+                        "won" => Finish::Won,
+                        "lost" => Finish::Lost,
+                        "unfinished" => Finish::Unfinished,
+                        _ => return Err(bad),
+                    })
+                }
+                _ => return Err(at(n, col, RecordError::BadKeyword)),
+            }
+        }
+
+        // The board on which the game started
+        let missing = |key| at(end, 1, RecordError::Missing(key));
+        let variant = set.variant.ok_or(missing("variant"))?;
+        let ranks = set.ranks.ok_or(missing("ranks"))?;
+        let start = match (set.layout, set.deal) {
+            (Some(text), _) => text.parse().map_err(|e: ParseBoardError| {
+                at(e.line, e.column, RecordError::Layout(e.reason))
+            })?,
+            (None, Some(deal)) => {
+                let mut b = Board::with_decks(
+                    variant,
+                    set.decks,
+                    set.suits,
+                    set.stacks.unwrap_or(variant.stacks()),
+                    ranks,
+                    set.reserves.unwrap_or(variant.reserves()),
+                );
+                b.deal(deal);
+                b
+            }
+            (None, None) => return Err(missing("deal")),
+        };
+        let finish = set.finish.ok_or(missing("result"))?;
+
+        let mut moves = Vec::with_capacity(set.moves.len());
+        for (n, col, w) in set.moves {
+            match parse_move(&start, &w) {
+                Some(m) => moves.push(m),
+                None => return Err(at(n, col, RecordError::BadMove)),
+            }
+        }
+        Ok(Record {
+            start,
+            moves,
+            finish,
+        })
+    }
+}
+
+/// The error returned when text does not describe a record
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseRecordError {
+    /// The line of the error counting from one
+    pub line: usize,
+    /// The column of the error counting from one
+    pub column: usize,
+    /// What is wrong
+    pub reason: RecordError,
+}
+
+impl fmt::Display for ParseRecordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.reason)
+    }
+}
+
+impl error::Error for ParseRecordError {}
+
+/// The reasons text does not describe a record
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecordError {
+    /// A line does not start with a keyword
+    BadKeyword,
+    /// A keyword has no value
+    NoValue,
+    /// The value of a keyword is not allowed
+    BadValue,
+    /// A line has more than it should
+    Extra,
+    /// The layout comes before the variant and the number of ranks
+    EarlyLayout,
+    /// The layout does not describe a board
+    Layout(TextError),
+    /// A word is not a move between places on the board
+    BadMove,
+    /// The record has no line with this keyword
+    Missing(&'static str),
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecordError::BadKeyword => write!(f, "Unknown keyword"),
+            RecordError::NoValue => write!(f, "Missing value"),
+            RecordError::BadValue => write!(f, "Bad value"),
+            RecordError::Extra => write!(f, "Extra text"),
+            RecordError::EarlyLayout => {
+                write!(f, "The layout must follow the variant and ranks")
+            }
+            RecordError::Layout(e) => write!(f, "{}", e),
+            RecordError::BadMove => write!(f, "Not a move"),
+            RecordError::Missing(key) => write!(f, "No {} given", key),
+        }
+    }
+}
+
+impl error::Error for RecordError {}
+
+/// The reasons a record cannot be replayed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReplayError {
+    /// A move is not allowed (move number counting from one, move,
+    /// reason)
+    Illegal {
+        number: usize,
+        name: String,
+        reason: MoveError,
+    },
+    /// The game did not end the way the record says it did
+    WrongFinish(Finish),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Illegal {
+                number,
+                name,
+                reason,
+            } => write!(f, "Move {} ({}) is illegal: {}", number, name, reason),
+            ReplayError::WrongFinish(Finish::Won) => {
+                write!(f, "The game was not won")
+            }
+            ReplayError::WrongFinish(_) => {
+                write!(f, "The game was won but is not recorded as won")
+            }
+        }
+    }
+}
+
+impl error::Error for ReplayError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A game on five clubs in which the C5 must make way for the C3
+    const LAYOUT: &str = "\
+variant streets
+ranks 5
+layout
+0 C-
+1 C3 C5
+2 C4 CA
+3 C2
+";
+
+    fn record(moves: &str, result: &str) -> Record {
+        format!("{}moves {}\nresult {}\n", LAYOUT, moves, result)
+            .parse()
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    // Reading the text written for a record gives back the record.
+    fn assert_round_trip(r: &Record) {
+        let text = r.to_string();
+        let read: Record = text.parse().unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(read.to_string(), text);
+        assert_eq!(read.start().to_string(), r.start().to_string());
+        assert_eq!(read.start().deal_number(), r.start().deal_number());
+        assert_eq!(read.moves(), r.moves());
+        assert_eq!(read.finish(), r.finish());
+    }
+
+    #[test]
+    fn deal_record_round_trip() {
+        let mut b = Board::with_decks(Variant::Fortress, 1, 4, 10, 13, 0);
+        b.deal(5);
+        // Make up to twelve moves, each the first one allowed
+        for _ in 0..12 {
+            match b.legal_moves().first() {
+                Some(&m) => b.apply(m).unwrap(),
+                None => break,
+            };
+        }
+        let r = Record::new(&b, Finish::Unfinished);
+        assert_eq!(r.start().deal_number(), Some(5));
+        assert!(!r.moves().is_empty());
+        assert_round_trip(&r);
+        let end = r.replay().unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(end.to_string(), b.to_string());
+    }
+
+    #[test]
+    fn layout_record_round_trip() {
+        let r = record("2-0 3-0 1-3 1-0 2-0 3-0", "won");
+        assert_eq!(r.start().deal_number(), None);
+        assert_eq!(r.moves().len(), 6);
+        assert_round_trip(&r);
+        assert!(r.replay().unwrap_or_else(|e| panic!("{}", e)).is_done());
+    }
+
+    #[test]
+    fn illegal_move_is_numbered() {
+        let r = record("2-0 3-0 1-0 1-3", "unfinished");
+        let e = ReplayError::Illegal {
+            number: 3,
+            name: "1-0".to_string(),
+            reason: MoveError::NotNext("C5".parse().unwrap()),
+        };
+        assert_eq!(r.replay().map(|_| ()), Err(e));
+    }
+
+    #[test]
+    fn finish_must_match_the_game() {
+        let r = record("2-0 3-0", "won");
+        let e = ReplayError::WrongFinish(Finish::Won);
+        assert_eq!(r.replay().map(|_| ()), Err(e));
+        let r = record("2-0 3-0 1-3", "lost");
+        let e = ReplayError::WrongFinish(Finish::Lost);
+        assert_eq!(r.replay().map(|_| ()), Err(e));
+    }
+
+    // Columns count characters, not bytes.
+    #[test]
+    fn bad_move_column_counts_characters() {
+        let text = format!("{}moves 2-0 \u{2663}-0 3-0\nresult lost\n", LAYOUT);
+        let e = text.parse::<Record>().err().expect("parsed a bad move");
+        assert_eq!((e.line, e.column), (8, 11));
+        assert_eq!(e.reason, RecordError::BadMove);
+    }
+}
@@ -11,16 +11,29 @@
 // implied warranty.
 
 use super::board::*;
-use super::window::Window;
+use super::record::{Finish, Record};
+use super::solver::{self, Limits, Outcome};
+use super::window::{Attr, Color, Key, Window};
+use super::Options;
+use std::fs::{self, File};
+use std::time::Duration;
 
 /// The structure that holds static information about the display.
 pub struct Screen {
     w: Window,
     version: &'static str,
-    prompt: i32,  // Row for the prompt
-    status: i32,  // Row for the status line
-    command: i32, // Row for commands
-    title: i32,   // Row for title
+    prompt: i32,           // Row for the prompt
+    status: i32,           // Row for the status line
+    command: i32,          // Row for commands
+    title: i32,            // Row for title
+    card_size: i32,        // Width used for each card
+    spacing: i32,          // Rows between places in the foundation column
+    hint: Option<Move>,    // Move highlighted as a hint
+    autoplay: bool,        // Make safe moves to the foundations
+    cursor: Option<usize>, // Position of the cursor in cursor mode
+    glyphs: bool,          // Draw suits as Unicode glyphs
+    color: bool,           // Does the terminal have colour?
+    quit: bool,            // Read no more keys
 }
 
 // Heights of various screen areas
@@ -35,6 +48,33 @@ const STACK_INDENT: i32 = 11;
 // Width giving the space used for a card
 const CARD_SIZE: i32 = 6;
 
+// Name of the file used to save and restore a game
+const SAVE_FILE_NAME: &str = "saa.sav";
+
+// Name of the file in which the record of a game is written
+const RECORD_FILE_NAME: &str = "saa.rec";
+
+// Longest time spent searching for a hint
+const HINT_TIME: Duration = Duration::from_secs(2);
+
+// Milliseconds to pause after each step of a run move
+const STEP_DELAY: i32 = 150;
+
+// Width of the screen assumed by the layout
+const SCREEN_WIDTH: i32 = 80;
+
+// Width of the text on the status line
+const STATUS_WIDTH: usize = (SCREEN_WIDTH - STACK_INDENT) as usize;
+
+// Colour pairs for red cards and error messages
+const RED_PAIR: i16 = 1;
+const ERROR_PAIR: i16 = 2;
+
+// Aliases for the keys of the stacks for use when there is no numeric
+// keypad
+const STACK_ALIASES: [char; 10] =
+    ['j', 'k', 'l', ';', 'u', 'i', 'o', 'p', '[', ']'];
+
 use Ans::*;
 
 // Used to report the result of a game
@@ -52,55 +92,159 @@ enum Resize {
     Quit,
 }
 
+// Used to report the result of a request to change the variant
+enum Choice {
+    Game(Variant),
+    Again,
+    Quit,
+}
+
 impl Screen {
     /// Create a fresh screen and compute layout parameters
     pub fn new(w: Window, version: &'static str) -> Screen {
-        let max_y = w.getmaxy();
-        let prompt = max_y - PROMPT_HEIGHT;
-        let status = prompt - STATUS_HEIGHT;
-        let command = status - COMMAND_HEIGHT;
-        let board = command - BOARD_HEIGHT;
-        let title = board - TITLE_HEIGHT;
-        Screen {
+        let color = Window::start_color();
+        if color {
+            Window::init_pair(RED_PAIR, Color::Red);
+            Window::init_pair(ERROR_PAIR, Color::Yellow);
+        }
+        let mut screen = Screen {
             w,
             version,
-            prompt,
-            status,
-            command,
-            title,
+            prompt: 0,
+            status: 0,
+            command: 0,
+            title: 0,
+            card_size: CARD_SIZE,
+            spacing: 2,
+            hint: None,
+            cursor: None,
+            autoplay: false,
+            glyphs: false,
+            color,
+            quit: false,
+        };
+        screen.rows();
+        screen
+    }
+
+    // Compute the rows used by the display from the height of the
+    // terminal.  The row of the title is moved by the layout of each
+    // game.
+    fn rows(&mut self) {
+        self.prompt = self.w.getmaxy() - PROMPT_HEIGHT;
+        self.status = self.prompt - STATUS_HEIGHT;
+        self.command = self.status - COMMAND_HEIGHT;
+        self.title = (self.command - BOARD_HEIGHT - TITLE_HEIGHT).max(0);
+    }
+
+    // Read a key.  When the terminal has been resized, the rows are
+    // recomputed before Key::Resize is returned, and the caller
+    // redraws its display.  Key::Eof is taken as a request to quit,
+    // and is all that is read once the player has quit.
+    fn get_key(&mut self) -> Key {
+        if self.quit {
+            return Key::Eof;
         }
+        let key = self.w.getch();
+        if key == Key::Resize {
+            self.rows();
+        }
+        key
     }
 
-    /// Play games stating with games of the given size
-    pub fn play(&mut self, nranks: usize) {
-        let mut b = Board::new(nranks);
+    /// Play games stating with games of the size given in the options
+    ///
+    /// When the options hold a record, its game is viewed first, and
+    /// play starts from the move chosen in the viewer.  Otherwise the
+    /// first game starts on the board in the options if there is one,
+    /// and uses the deal number in the options if there is one.
+    /// Other games use deal numbers chosen at random.
+    pub fn play(&mut self, opts: &Options) {
+        self.autoplay = opts.autoplay;
+        // Letters are used when the locale cannot encode the glyphs.
+        self.glyphs = opts.glyphs && self.w.is_wide();
+        let mut given = opts.board.clone();
+        if let Some(record) = &opts.record {
+            match self.view(record) {
+                Some(b) => given = Some(b),
+                None => return,
+            }
+        }
+        let mut dealt = given.is_some();
+        let reserves = opts.reserves.unwrap_or(opts.variant.reserves());
+        let mut b = given.unwrap_or_else(|| {
+            Board::with_decks(
+                opts.variant,
+                opts.decks,
+                opts.suits,
+                opts.stacks,
+                opts.ranks,
+                reserves,
+            )
+        });
+        // A given board is the first game, so the deal is not used
+        let mut deal = if dealt { None } else { opts.deal };
 
         loop {
             // Play one game
+            if !dealt {
+                b.deal(deal.take().unwrap_or_else(random_deal));
+            }
+            dealt = false;
             let status = self.play_one_game(&mut b);
             self.clear_status();
-            match status {
-                Win => self.w.addstr("You won!"),
-                Lose => self.w.addstr("You lose."),
+            let finish = match status {
+                Win => {
+                    self.w.addstr("You won!  ");
+                    Finish::Won
+                }
+                Lose => {
+                    self.w.addstr("You lose.  ");
+                    Finish::Lost
+                }
             };
+            self.record_game(&b, finish);
             // Ask what to do next
-            self.clear_prompt();
-            self.w.addstr(
-                "Press space to play again, \
-                 x to exit, or r to change game size. ",
-            );
             loop {
-                match self.w.getch() {
-                    'x' => return,
-                    ' ' => break,
-                    'r' => match self.resize() {
+                self.clear_prompt();
+                self.w.addstr(
+                    "Space plays again, x exits, \
+                     r changes size, v changes variant. ",
+                );
+                match self.get_key() {
+                    Key::Resize => self.show_game(&b),
+                    Key::Char('x') | Key::Eof => return,
+                    Key::Char(' ') => break,
+                    Key::Char('r') => match self.resize(b.variant()) {
                         Rank(rank) => {
-                            b = Board::new(rank);
+                            b = Board::with_decks(
+                                b.variant(),
+                                b.decks(),
+                                b.suits(),
+                                b.stacks(),
+                                rank,
+                                b.reserves(),
+                            );
                             break;
                         }
                         Quit => return,
                         Again => break,
                     },
+                    Key::Char('v') => match self.choose_variant(b.variant()) {
+                        Choice::Game(variant) => {
+                            b = Board::with_decks(
+                                variant,
+                                b.decks(),
+                                b.suits(),
+                                variant.stacks(),
+                                b.ranks(),
+                                variant.reserves(),
+                            );
+                            break;
+                        }
+                        Choice::Quit => return,
+                        Choice::Again => break,
+                    },
                     _ => continue, // Invalid response, try again
                 }
             }
@@ -109,8 +253,9 @@ impl Screen {
     }
 
     fn play_one_game(&mut self, b: &mut Board) -> Ans {
-        b.deal();
+        self.cursor = None;
         self.show_game(b);
+        self.autoplay(b);
         loop {
             if b.is_done() {
                 return Win;
@@ -124,22 +269,54 @@ impl Screen {
     // Display a game
 
     fn show_game(&mut self, b: &Board) {
+        while !self.layout(b) {
+            if !self.too_small(b) {
+                return;
+            }
+        }
         self.w.clear();
         // Title
         self.w.mov(self.title, STACK_INDENT);
-        self.w.addstr("Streets and Alleys");
+        self.w.addstr(b.variant().name());
+        if b.reserves() != b.variant().reserves() {
+            self.w.addstr(", practice");
+        }
+        if b.decks() != 1 {
+            self.w.addstr(&format!(", {} decks", b.decks()));
+        }
+        if b.suits() != MAX_SUITS {
+            self.w.addstr(&format!(", {} suits", b.suits()));
+        }
+        if b.stacks() != b.variant().stacks() {
+            self.w.addstr(&format!(", {} stacks", b.stacks()));
+        }
+        if let Some(n) = b.deal_number() {
+            self.w.addstr(&format!(", deal {}", n));
+        }
         // Board
         self.show_board(b);
         // Commands
         self.w.mov(self.command, 0);
         self.w.addstr("Commands:");
-        for i in -1..STACKS as i32 {
-            self.goto_stack_top(i, 0);
-            self.w.addch(i2char(i + 1));
+        self.goto_stack_top(-1, 0);
+        self.w.addstr("0,");
+        for i in 0..b.stacks() {
+            self.goto_stack_top(i as i32, 0);
+            self.w.addch(b.place_label(i));
             self.w.addch(',');
         }
-        self.goto_stack_top(8, 0);
-        self.w.addstr("q, or ?.");
+        let stacks = b.stacks() as i32;
+        let rest = "q, r, s, or ?.";
+        let x = STACK_INDENT + self.card_size * (stacks + 1);
+        if x + rest.len() as i32 <= SCREEN_WIDTH {
+            self.goto_stack_top(stacks, 0);
+        } else {
+            self.w.mov(self.command + 1, STACK_INDENT);
+        }
+        self.w.addstr(rest);
+        // Cursor
+        self.cursor = self.cursor.filter(|&i| i < positions(b));
+        self.show_cursor(b, true);
         // Status
         self.w.mov(self.status, 0);
         self.w.addstr("Status:");
@@ -150,38 +327,165 @@ impl Screen {
         self.w.addstr("Prompt:");
     }
 
+    // Fit the board to the screen.  Cards are placed closer together
+    // when there are many stacks, foundations are placed in adjacent
+    // rows when there are more than four, and the title moves up
+    // when the stacks may grow taller than usual.  Returns false when
+    // the terminal is too small for the board.
+    fn layout(&mut self, b: &Board) -> bool {
+        let places = b.stacks() as i32 + 1;
+        self.card_size = CARD_SIZE.min((SCREEN_WIDTH - STACK_INDENT) / places);
+        self.spacing = if b.foundations() > MAX_SUITS { 1 } else { 2 };
+        let title = self.command - self.board_height(b) - TITLE_HEIGHT;
+        self.title = title.max(0);
+        title >= 0 && self.w.getmaxx() >= SCREEN_WIDTH
+    }
+
+    // Rows used by the foundation column and the stacks
+    fn board_height(&self, b: &Board) -> i32 {
+        let cards = b.decks() * b.suits() * b.ranks();
+        let dealt = cards.div_ceil(b.stacks());
+        let stack = (dealt + b.ranks() - 1) as i32;
+        let column = self.spacing * (b.foundations() + b.reserves() + 1) as i32;
+        BOARD_HEIGHT.max(stack).max(column)
+    }
+
+    // Ask for a larger terminal, and wait until it has been resized.
+    // Returns false when there is no more input.  Quitting here ends
+    // the input, so every prompt that follows quits as well.
+    fn too_small(&mut self, b: &Board) -> bool {
+        let height = TITLE_HEIGHT
+            + self.board_height(b)
+            + COMMAND_HEIGHT
+            + STATUS_HEIGHT
+            + PROMPT_HEIGHT;
+        self.w.clear();
+        self.w.mov(0, 0);
+        self.w.addstr("The terminal is too small for this game.");
+        self.w.mov(1, 0);
+        self.w.addstr(&format!(
+            "It must be at least {} columns by {} rows.",
+            SCREEN_WIDTH, height
+        ));
+        self.w.mov(2, 0);
+        self.w.addstr("Enlarge the terminal or type q to quit.");
+        loop {
+            match self.get_key() {
+                Key::Resize => return true,
+                Key::Char('q') | Key::Eof => {
+                    self.quit = true;
+                    return false;
+                }
+                _ => (),
+            }
+        }
+    }
+
     fn show_board(&mut self, b: &Board) {
         // Foundations
-        for i in 0..SUITS {
-            self.show_foundation(b, i);
+        self.show_foundations(b);
+        // Reserve cells
+        for i in 0..b.reserves() {
+            self.goto_reserve(b, i);
+            self.w.mov(self.w.getyx().0, STACK_INDENT - 3);
+            self.w.addch(b.place_label(b.stacks() + i));
+            self.show_reserve(b, i);
         }
         // Stacks
-        for i in 0..STACKS {
-            let mut j = 1;
-            for c in b.stack_iter(i) {
+        for i in 0..b.stacks() {
+            for (j, c) in (1..).zip(b.stack_iter(i)) {
                 self.goto_stack_top(i as i32, j);
                 self.show_card(*c);
-                j += 1;
             }
         }
     }
 
-    fn show_foundation(&mut self, b: &Board, i: usize) {
-        self.goto_foundation(i as i32);
-        self.show_card(b.foundation_ref(i));
+    // With more than one deck, a move may change any foundation of
+    // the suit, so they are all shown.
+    fn show_foundations(&mut self, b: &Board) {
+        for f in 0..b.foundations() {
+            self.goto_foundation(f as i32);
+            match b.foundation_ref(f) {
+                Some(c) => self.show_card(c),
+                None => {
+                    self.show_suit(b.foundation_suit(f));
+                    self.w.addch('-');
+                }
+            }
+        }
     }
 
     fn goto_foundation(&mut self, i: i32) {
-        self.goto_stack_top(-1, 2 * (i + 1));
+        self.goto_stack_top(-1, self.spacing * (i + 1));
+    }
+
+    fn show_reserve(&mut self, b: &Board, i: usize) {
+        self.goto_reserve(b, i);
+        match b.reserve_ref(i) {
+            Some(c) => self.show_card(c),
+            None => {
+                self.w.addstr("--");
+            }
+        }
+    }
+
+    // Reserve cells go above the foundations leaving a gap between
+    // them.
+    fn goto_reserve(&mut self, b: &Board, i: usize) {
+        let i = (b.foundations() + i + 1) as i32;
+        self.goto_stack_top(-1, self.spacing * (i + 1));
+    }
+
+    // Show the top card of a place, highlighted with an attribute
+    // when one is given
+    fn show_top(&mut self, b: &Board, p: usize, attr: Option<Attr>) {
+        if let Some(c) = b.last_card(p) {
+            self.goto_top(b, p);
+            if let Some(a) = attr {
+                self.w.attron(a);
+            }
+            self.show_card(c);
+            if let Some(a) = attr {
+                self.w.attroff(a);
+            }
+        }
+    }
+
+    // Move to the top card of a place
+    fn goto_top(&mut self, b: &Board, p: usize) {
+        if b.is_reserve(p) {
+            self.goto_reserve(b, p - b.stacks());
+        } else {
+            self.goto_stack_top(p as i32, b.stack_len(p) as i32);
+        }
+    }
+
+    // Show a place after a card is taken from it
+    fn show_taken(&mut self, b: &Board, p: usize) {
+        if b.is_reserve(p) {
+            self.show_reserve(b, p - b.stacks());
+        } else {
+            self.erase_above_stack(b, p);
+        }
+    }
+
+    // Show a place after a card is put in it
+    fn show_put(&mut self, b: &Board, p: usize) {
+        if b.is_reserve(p) {
+            self.show_reserve(b, p - b.stacks());
+        } else {
+            self.show_top_of_stack(b, p);
+        }
     }
 
     fn goto_stack_top(&mut self, p: i32, h: i32) {
         self.w
-            .mov(self.command - h, STACK_INDENT + CARD_SIZE * (p + 1));
+            .mov(self.command - h, STACK_INDENT + self.card_size * (p + 1));
     }
 
-    fn erase_top_of_stack(&mut self, b: &Board, p: usize) {
-        self.goto_stack_top(p as i32, b.stack_len(p) as i32);
+    // Erase the place just above the top of a stack
+    fn erase_above_stack(&mut self, b: &Board, p: usize) {
+        self.goto_stack_top(p as i32, b.stack_len(p) as i32 + 1);
         self.w.addstr("  ");
     }
 
@@ -190,9 +494,51 @@ impl Screen {
         self.show_card(b.last_card(p).expect("no card to show on stack"));
     }
 
+    // Hearts and diamonds are red when the terminal has colour.
     fn show_card(&mut self, c: Card) {
-        self.w.addch(show_suit(c));
-        self.w.addch(show_rank(c));
+        let attrs = self.w.attr_get();
+        if self.color && c.suit().is_red() {
+            self.w.attron(Attr::Pair(RED_PAIR));
+        }
+        self.show_suit(c.suit());
+        self.w.addch(c.rank().to_char());
+        self.w.attrset(attrs);
+    }
+
+    fn show_suit(&mut self, s: Suit) {
+        if self.glyphs {
+            self.w.add_wch(s.to_glyph());
+        } else {
+            self.w.addch(s.to_char());
+        }
+    }
+
+    // Error messages are written in bold on the status line, and in
+    // their own colour when the terminal has colour.
+
+    fn show_error(&mut self, msg: &str) {
+        self.start_error();
+        self.w.addstr(msg);
+        self.end_error();
+    }
+
+    fn start_error(&mut self) {
+        self.clear_status();
+        self.error_on();
+    }
+
+    fn error_on(&mut self) {
+        self.w.attron(Attr::Bold);
+        if self.color {
+            self.w.attron(Attr::Pair(ERROR_PAIR));
+        }
+    }
+
+    fn end_error(&mut self) {
+        self.w.attroff(Attr::Bold);
+        if self.color {
+            self.w.attroff(Attr::Pair(ERROR_PAIR));
+        }
     }
 
     // Clear status and prompt
@@ -212,136 +558,809 @@ impl Screen {
     fn pick_up_card(&mut self, b: &mut Board) -> bool {
         self.clear_prompt();
         self.w.addstr("Move from stack ");
-        let from = self.get_cmd();
+        let from = self.get_cmd(b);
+        self.clear_hint(b);
         match from {
-            '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' => {
-                self.place_card(b, from)
+            Key::Resize => {
+                self.show_game(b);
+                false
             }
-            'q' => true,
-            '?' => self.help(b),
-            _ => {
+            Key::Char('q') | Key::Eof => true,
+            Key::Char('r') => self.restore_game(b),
+            Key::Char('s') => self.save_game(b),
+            Key::Char('w') => {
                 self.clear_status();
-                self.w.addstr("Bad input.  Type ? for help.");
+                self.record_game(b, Finish::Unfinished);
                 false
             }
+            Key::Char('z') => self.undo(b),
+            Key::Char('y') => self.redo(b),
+            Key::Char('h') => self.hint(b),
+            Key::Char('m') => self.move_run(b),
+            Key::Char('t') => self.toggle_autoplay(b),
+            Key::Char('?') => self.help(b),
+            _ => match self.key_place(b, from) {
+                Some(p) => self.place_card(b, p),
+                None => {
+                    self.show_error("Bad input.  Type ? for help.");
+                    false
+                }
+            },
         }
     }
 
     // Get place to put card
 
-    fn place_card(&mut self, b: &mut Board, from: char) -> bool {
-        let s = char2u(from) - 1; // Stack with picked up card
+    fn place_card(&mut self, b: &mut Board, s: usize) -> bool {
         match b.last_card(s) {
             None => {
-                // Stack has no cards!
-                self.clear_status();
-                self.w.addstr("There is no card in stack ");
-                self.w.addch(from);
+                // Place has no cards!
+                self.start_error();
+                self.w.addstr("There is no card in ");
+                self.w.addstr(&place_name(b, s));
                 self.w.addch('.');
+                self.end_error();
                 false
             }
             Some(c) => {
                 self.clear_prompt();
                 self.w.addstr("Move ");
                 self.show_card(c);
-                self.w.addstr(" from stack ");
-                self.w.addch(from);
+                self.w.addstr(" from ");
+                self.w.addstr(&place_name(b, s));
                 self.w.addstr(" to ");
-                let to = self.get_cmd();
+                self.show_top(b, s, Some(Attr::Reverse));
+                let to = self.get_cmd(b);
+                self.show_top(b, s, None);
                 match to {
-                    // Move card to destination
-                    '0' => self.move_to_foundation(b, s, c),
-                    '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' => {
-                        self.move_to_stack(b, from, s, c, to)
-                    }
-                    'q' => true,
-                    '?' => self.help(b),
-                    _ => {
-                        self.clear_status();
-                        self.w.addstr("Bad input.  Type ? for help.");
-                        false
+                    // Keep the card picked up after redrawing
+                    Key::Resize => {
+                        self.show_game(b);
+                        self.place_card(b, s)
                     }
+                    // Move card to destination
+                    Key::Char('0') => self.move_to_foundation(b, s, c),
+                    Key::Char('q') | Key::Eof => true,
+                    Key::Char('r') => self.restore_game(b),
+                    Key::Char('s') => self.save_game(b),
+                    Key::Char('?') => self.help(b),
+                    _ => match self.key_place(b, to) {
+                        Some(t) => self.move_to_stack(b, s, c, t),
+                        None => {
+                            self.show_error("Bad input.  Type ? for help.");
+                            false
+                        }
+                    },
                 }
             }
         }
     }
 
     // Implement aliases for commands
-    fn get_cmd(&self) -> char {
-        match self.w.getch() {
-            ' ' => '0', // Aliases for use when there
-            'j' => '1', // is no numeric keyad.
-            'k' => '2',
-            'l' => '3',
-            ';' => '4',
-            'u' => '5',
-            'i' => '6',
-            'o' => '7',
-            'p' => '8',
-            c => c,
+    fn get_cmd(&mut self, b: &Board) -> Key {
+        loop {
+            let key = match self.get_key() {
+                Key::Left => {
+                    self.move_cursor(b, positions(b) - 1);
+                    continue;
+                }
+                Key::Right => {
+                    self.move_cursor(b, 1);
+                    continue;
+                }
+                Key::Enter | Key::Char(' ') if self.cursor.is_some() => {
+                    return Key::Char(self.cursor_label(b));
+                }
+                Key::Char(' ') => Key::Char('0'),
+                Key::Char(c) => {
+                    match STACK_ALIASES.iter().position(|a| *a == c) {
+                        Some(i) if i < b.stacks() => {
+                            Key::Char(b.place_label(i))
+                        }
+                        _ => Key::Char(c),
+                    }
+                }
+                key => key,
+            };
+            // Selecting a place directly leaves cursor mode
+            if key == Key::Char('0') || self.key_place(b, key).is_some() {
+                self.show_cursor(b, false);
+                self.cursor = None;
+            }
+            return key;
+        }
+    }
+
+    // Cursor mode.  The cursor is shown by highlighting the label of
+    // the foundation column, a reserve cell, or a stack.  Its
+    // positions run from the foundation column through the reserve
+    // cells to the stacks.
+
+    // Move the cursor right by some positions, wrapping around.  The
+    // first move shows the cursor on the foundation column.
+    fn move_cursor(&mut self, b: &Board, by: usize) {
+        self.show_cursor(b, false);
+        self.cursor = Some(match self.cursor {
+            Some(i) => (i + by) % positions(b),
+            None => 0,
+        });
+        self.show_cursor(b, true);
+    }
+
+    // The key that selects the place under the cursor
+    fn cursor_label(&self, b: &Board) -> char {
+        match self.cursor {
+            Some(0) | None => '0',
+            Some(i) if i <= b.reserves() => b.place_label(b.stacks() + i - 1),
+            Some(i) => b.place_label(i - 1 - b.reserves()),
+        }
+    }
+
+    // Draw the label under the cursor, highlighted when on is true,
+    // and put the cursor of the terminal back where it was.
+    fn show_cursor(&mut self, b: &Board, on: bool) {
+        let i = match self.cursor {
+            Some(i) => i,
+            None => return,
+        };
+        let (y, x) = self.w.getyx();
+        if i == 0 {
+            self.goto_stack_top(-1, 0);
+        } else if i <= b.reserves() {
+            self.goto_reserve(b, i - 1);
+            self.w.mov(self.w.getyx().0, STACK_INDENT - 3);
+        } else {
+            self.goto_stack_top((i - 1 - b.reserves()) as i32, 0);
+        }
+        if on {
+            self.w.attron(Attr::Reverse);
+        }
+        self.w.addch(self.cursor_label(b));
+        if on {
+            self.w.attroff(Attr::Reverse);
+        }
+        self.w.mov(y, x);
+    }
+
+    // The stack or reserve cell selected by a key
+    fn key_place(&self, b: &Board, key: Key) -> Option<usize> {
+        match key {
+            Key::Char(c) => b.label_place(c),
+            _ => None,
         }
     }
 
     fn move_to_foundation(&mut self, b: &mut Board, s: usize, c: Card) -> bool {
         self.show_card(c);
-        let to = card2suit(c);
-        if c == SUITS + b.foundation_ref(to) {
-            self.erase_top_of_stack(b, s);
-            b.pop_card(s);
-            b.foundation_set(to, c);
-            self.show_foundation(b, to);
+        if b.apply(Move::Foundation(s)).is_ok() {
+            self.show_taken(b, s);
+            self.show_foundations(b);
             self.clear_status();
             self.w.addstr("The ");
             self.show_card(c);
-            self.w.addstr(" was");
+            self.w.addstr(" was moved to the foundation.");
+            self.autoplay(b);
         } else {
             // Cannot move card to foundation
-            self.clear_status();
+            self.start_error();
             self.w.addstr("The ");
             self.show_card(c);
-            self.w.addstr(" cannot be");
+            self.w.addstr(" cannot be moved to the foundation.");
+            self.end_error();
         }
-        self.w.addstr(" moved to the foundation.");
         false
     }
 
-    #[allow(clippy::many_single_char_names)]
     fn move_to_stack(
         &mut self,
         b: &mut Board,
-        from: char,
         s: usize,
         c: Card,
-        to: char,
+        t: usize,
     ) -> bool {
-        let t = char2u(to) - 1;
-        // Can card be moved to this stack?
-        let can_move = match b.last_card(t) {
-            None => true,
-            Some(d) => card2rank(d) == 1 + card2rank(c),
-        };
-        if can_move {
-            self.erase_top_of_stack(b, s);
-            b.pop_card(s);
-            b.push_card(t, c);
-            self.show_top_of_stack(b, t);
+        let moved = b.apply(Move::Stack(s, t)).is_ok();
+        if moved {
+            self.show_taken(b, s);
+            self.show_put(b, t);
             self.clear_status();
             self.w.addstr("Moved the ");
             self.show_card(c);
         } else {
-            self.clear_status();
+            self.start_error();
             self.w.addstr("The ");
             self.show_card(c);
             self.w.addstr(" cannot be moved");
         }
-        self.w.addstr(" from stack ");
-        self.w.addch(from);
-        self.w.addstr(" to stack ");
-        self.w.addch(to);
+        self.w.addstr(" from ");
+        self.w.addstr(&place_name(b, s));
+        self.w.addstr(" to ");
+        self.w.addstr(&place_name(b, t));
         self.w.addstr(".");
+        if moved {
+            self.autoplay(b);
+        } else {
+            self.end_error();
+        }
+        false
+    }
+
+    // Make the moves to the foundations that can never hurt when
+    // automatic play is on.  Each move is shown as it is made.
+
+    fn autoplay(&mut self, b: &mut Board) {
+        if !self.autoplay {
+            return;
+        }
+        // The moves are part of the move that made them safe, so they
+        // are undone with it.
+        while let Some(m) = b.safe_move() {
+            let c = b.make_joined_move(m);
+            self.show_made(b, m);
+            self.clear_status();
+            self.w.addstr("The ");
+            self.show_card(c);
+            self.w.addstr(" was moved to the foundation automatically.");
+        }
+    }
+
+    fn toggle_autoplay(&mut self, b: &mut Board) -> bool {
+        self.autoplay = !self.autoplay;
+        self.clear_status();
+        if self.autoplay {
+            self.w.addstr("Automatic play of safe moves is on.");
+            self.autoplay(b);
+        } else {
+            self.w.addstr("Automatic play of safe moves is off.");
+        }
+        false
+    }
+
+    // Move an ordered run of cards from one stack to another.  The
+    // cards are moved one at a time using empty stacks to hold cards
+    // along the way, and each step is shown as it is made.
+
+    fn move_run(&mut self, b: &mut Board) -> bool {
+        self.clear_prompt();
+        self.w.addstr("Move run from stack ");
+        let from = self.get_cmd(b);
+        let s = match self.key_place(b, from) {
+            Some(s) if !b.is_reserve(s) => s,
+            _ if from == Key::Eof => return true,
+            _ if from == Key::Resize => {
+                self.show_game(b);
+                return false;
+            }
+            _ => {
+                self.show_error("Bad input.  Type ? for help.");
+                return false;
+            }
+        };
+        if b.stack_len(s) == 0 {
+            self.start_error();
+            self.w.addstr("There is no card in stack ");
+            self.w.addch(b.place_label(s));
+            self.w.addch('.');
+            self.end_error();
+            return false;
+        }
+        self.clear_prompt();
+        self.w.addstr("Move run from stack ");
+        self.w.addch(b.place_label(s));
+        self.w.addstr(" to stack ");
+        let to = self.get_cmd(b);
+        let t = match self.key_place(b, to) {
+            Some(t) if !b.is_reserve(t) => t,
+            _ if to == Key::Eof => return true,
+            _ if to == Key::Resize => {
+                self.show_game(b);
+                return false;
+            }
+            _ => {
+                self.show_error("Bad input.  Type ? for help.");
+                return false;
+            }
+        };
+        let moves = match b.plan_run(s, t) {
+            Ok(moves) => moves,
+            Err(e) => {
+                self.show_error(&format!("{}.", e));
+                return false;
+            }
+        };
+        let mut steps = String::new();
+        for (i, m) in moves.iter().enumerate() {
+            let c = if i == 0 {
+                b.make_move(*m)
+            } else {
+                b.make_joined_move(*m)
+            };
+            self.show_made(b, *m);
+            self.clear_status();
+            self.w.addstr(&format!(
+                "Step {} of {}: moved the ",
+                i + 1,
+                moves.len()
+            ));
+            self.show_move(b, *m, c);
+            self.w.refresh();
+            Window::napms(STEP_DELAY);
+            if let Move::Stack(from, to) = m {
+                steps.push_str(&format!(
+                    " {}-{}",
+                    b.place_label(*from),
+                    b.place_label(*to)
+                ));
+            }
+        }
+        self.clear_status();
+        let done = format!("Moved the run in {} steps:", moves.len());
+        if done.len() + steps.len() < STATUS_WIDTH {
+            self.w.addstr(&format!("{}{}.", done, steps));
+        } else {
+            self.w
+                .addstr(&format!("Moved the run in {} steps.", moves.len()));
+        }
+        self.autoplay(b);
+        false
+    }
+
+    // Undo and redo moves.  Only the cells changed by a move are
+    // redisplayed.  The steps of a run move are undone and redone
+    // together.
+
+    fn undo(&mut self, b: &mut Board) -> bool {
+        let mut undone = 0;
+        let mut last = None;
+        while let Some((m, c)) = b.undo() {
+            self.show_undone(b, m);
+            undone += 1;
+            last = Some((m, c));
+            if !b.redo_is_joined() {
+                break;
+            }
+        }
+        self.clear_status();
+        match last {
+            None => {
+                self.w.addstr("There is no move to undo.");
+            }
+            Some((m, c)) if undone == 1 => {
+                self.w.addstr("Took back moving the ");
+                self.show_move(b, m, c);
+            }
+            Some(_) => {
+                self.w.addstr(&format!(
+                    "Took back a move made in {} steps.",
+                    undone
+                ));
+            }
+        }
+        false
+    }
+
+    fn redo(&mut self, b: &mut Board) -> bool {
+        let mut redone = 0;
+        let mut last = None;
+        while let Some((m, c)) = b.redo() {
+            self.show_made(b, m);
+            redone += 1;
+            last = Some((m, c));
+            if !b.redo_is_joined() {
+                break;
+            }
+        }
+        self.clear_status();
+        match last {
+            None => {
+                self.w.addstr("There is no move to redo.");
+            }
+            Some((m, c)) if redone == 1 => {
+                self.w.addstr("Redid moving the ");
+                self.show_move(b, m, c);
+            }
+            Some(_) => {
+                self.w
+                    .addstr(&format!("Redid a move made in {} steps.", redone));
+            }
+        }
+        false
+    }
+
+    // Show the cells changed by a move just made
+    fn show_made(&mut self, b: &Board, m: Move) {
+        self.show_taken(b, m.source());
+        match m {
+            Move::Stack(_, to) => self.show_put(b, to),
+            Move::Foundation(_) => self.show_foundations(b),
+        }
+    }
+
+    // Show the cells changed by a move just taken back
+    fn show_undone(&mut self, b: &Board, m: Move) {
+        match m {
+            Move::Stack(from, to) => {
+                self.show_taken(b, to);
+                self.show_put(b, from);
+            }
+            Move::Foundation(from) => {
+                self.show_foundations(b);
+                self.show_put(b, from);
+            }
+        }
+    }
+
+    // Suggest a move found by searching for a win from the current
+    // position.  The search is limited in time so that the game does
+    // not freeze.  The cards involved in the move are highlighted
+    // until the next command is read.
+
+    fn hint(&mut self, b: &Board) -> bool {
+        self.clear_status();
+        self.w.addstr("Searching for a winning line...");
+        self.w.refresh();
+        let limits = Limits {
+            time: Some(HINT_TIME),
+            ..Limits::default()
+        };
+        let outcome = solver::solve(b, &limits);
+        self.clear_status();
+        match outcome {
+            Outcome::Solved(moves) => match moves.first() {
+                Some(m) => {
+                    let c = b.last_card(m.source()).expect("no card to move");
+                    self.show_hint(b, *m, true);
+                    self.hint = Some(*m);
+                    self.w.addstr("Move the ");
+                    self.show_move(b, *m, c);
+                    self.w.addstr("  ");
+                    self.show_reason(b, &moves);
+                }
+                None => {
+                    self.w.addstr("The game is won.");
+                }
+            },
+            Outcome::Unwinnable => {
+                self.w.addstr("No winning line exists from here.");
+            }
+            Outcome::GaveUp => {
+                self.w
+                    .addstr("No winning line was found in the time allowed.");
+            }
+        }
+        false
+    }
+
+    // Explain why the first move of a winning line is a good one:
+    // it wins, it empties a stack, it frees a card that goes to its
+    // foundation next, or else it starts a win in so many moves.
+    fn show_reason(&mut self, b: &Board, moves: &[Move]) {
+        let m = moves[0];
+        let from = m.source();
+        let mut after = b.clone();
+        after.make_move(m);
+        if after.is_done() {
+            self.w.addstr("It wins the game.");
+        } else if !b.is_reserve(from) && after.stack_len(from) == 0 {
+            self.w
+                .addstr(&format!("It empties {}.", place_name(b, from)));
+        } else {
+            match (moves.get(1), after.last_card(from)) {
+                (Some(Move::Foundation(f)), Some(c)) if *f == from => {
+                    self.w.addstr("It frees the ");
+                    self.show_card(c);
+                    self.w.addch('.');
+                }
+                _ => {
+                    let n = moves.len();
+                    self.w.addstr(&format!("It wins in {} moves.", n));
+                }
+            }
+        }
+    }
+
+    // Remove the highlighting of a hint
+    fn clear_hint(&mut self, b: &Board) {
+        if let Some(m) = self.hint.take() {
+            self.show_hint(b, m, false);
+        }
+    }
+
+    // Show the cards involved in a hint, highlighted or not.  The
+    // cursor is left where it was.
+    fn show_hint(&mut self, b: &Board, m: Move, highlight: bool) {
+        let (y, x) = self.w.getyx();
+        if highlight {
+            self.w.attron(Attr::Standout);
+        }
+        let from = m.source();
+        let c = b.last_card(from).expect("no card to move");
+        self.goto_top(b, from);
+        self.show_card(c);
+        match m {
+            Move::Stack(_, to) => match b.last_card(to) {
+                Some(d) => {
+                    self.goto_top(b, to);
+                    self.show_card(d);
+                }
+                None if b.is_reserve(to) => {
+                    self.show_reserve(b, to - b.stacks())
+                }
+                None => {
+                    self.goto_stack_top(to as i32, 1);
+                    self.w.addstr("  ");
+                }
+            },
+            Move::Foundation(_) => self.show_foundations(b),
+        }
+        if highlight {
+            self.w.attroff(Attr::Standout);
+        }
+        self.w.mov(y, x);
+    }
+
+    // Describe a move on the status line
+    fn show_move(&mut self, b: &Board, m: Move, c: Card) {
+        self.show_card(c);
+        self.w.addstr(" from ");
+        self.w.addstr(&place_name(b, m.source()));
+        match m {
+            Move::Stack(_, to) => {
+                self.w.addstr(" to ");
+                self.w.addstr(&place_name(b, to));
+            }
+            Move::Foundation(_) => {
+                self.w.addstr(" to the foundation");
+            }
+        }
+        self.w.addch('.');
+    }
+
+    // Save and restore games
+
+    fn restore_game(&mut self, b: &mut Board) -> bool {
+        self.clear_status();
+        self.clear_prompt();
+        self.w.addstr("Type space to restore game in file ");
+        self.w.addstr(SAVE_FILE_NAME);
+        self.w.addstr(". ");
+        let key = self.get_key();
+        match key {
+            Key::Resize => self.show_game(b),
+            Key::Eof => return true,
+            _ => (),
+        }
+        self.clear_status();
+        if key != Key::Char(' ') {
+            self.w
+                .addstr("The restoration of the old game was aborted.");
+            return false;
+        }
+        let mut f = match File::open(SAVE_FILE_NAME) {
+            Ok(f) => f,
+            Err(_) => {
+                self.show_error(&format!(
+                    "Restore error: Cannot open {}.  Game not restored.",
+                    SAVE_FILE_NAME
+                ));
+                return false;
+            }
+        };
+        match Board::restore(&mut f) {
+            Ok(saved) => {
+                *b = saved;
+                self.show_game(b);
+            }
+            Err(e) => {
+                self.show_error(&format!(
+                    "Restore error: {}.  Game not restored.",
+                    e
+                ));
+            }
+        }
+        false
+    }
+
+    fn save_game(&mut self, b: &Board) -> bool {
+        self.clear_status();
+        self.clear_prompt();
+        self.w.addstr("Type space to save game in file ");
+        self.w.addstr(SAVE_FILE_NAME);
+        self.w.addstr(". ");
+        let key = self.get_key();
+        match key {
+            Key::Resize => self.show_game(b),
+            Key::Eof => return true,
+            _ => (),
+        }
+        self.clear_status();
+        if key != Key::Char(' ') {
+            self.w.addstr("The saving of the game was aborted.");
+            return false;
+        }
+        let mut f = match File::create(SAVE_FILE_NAME) {
+            Ok(f) => f,
+            Err(_) => {
+                self.show_error(&format!(
+                    "Save error: Cannot open {}.  Game not saved.",
+                    SAVE_FILE_NAME
+                ));
+                return false;
+            }
+        };
+        match b.save(&mut f) {
+            Ok(()) => {
+                self.w.addstr("Game saved.");
+            }
+            Err(_) => {
+                self.show_error("Save error: Write failed.  Game not saved.")
+            }
+        }
         false
     }
 
+    // View a recorded game.  The moves of the record are made and
+    // then taken back, so that stepping through them is redoing and
+    // undoing them.  Branching off into live play keeps the moves not
+    // yet viewed as moves that can be redone.
+
+    fn view(&mut self, record: &Record) -> Option<Board> {
+        let mut b = record.start().clone();
+        let mut illegal = None;
+        for (i, m) in record.moves().iter().enumerate() {
+            if b.apply(*m).is_err() {
+                illegal = Some(i + 1);
+                break;
+            }
+        }
+        let total = b.moves_made().len();
+        while b.undo().is_some() {}
+        let mut at = 0;
+        self.show_game(&b);
+        self.show_counter(at, total);
+        self.w.addch('.');
+        if let Some(n) = illegal {
+            self.w.addstr(&format!(
+                "  The moves from move {} on are illegal.",
+                n
+            ));
+        }
+        loop {
+            self.clear_prompt();
+            self.w.addstr(
+                "n next, p previous, < start, > end, g go to, \
+                 l plays, q quits. ",
+            );
+            match self.get_key() {
+                Key::Resize => {
+                    at = self.go_to_move(&mut b, at, at, total);
+                }
+                Key::Char('n') | Key::Char(' ') => match b.redo() {
+                    Some((m, c)) => {
+                        at += 1;
+                        self.show_made(&b, m);
+                        self.show_counter(at, total);
+                        self.w.addstr(": moved the ");
+                        self.show_move(&b, m, c);
+                    }
+                    None => {
+                        self.clear_status();
+                        self.w.addstr("There are no more moves.");
+                    }
+                },
+                Key::Char('p') => match b.undo() {
+                    Some((m, c)) => {
+                        at -= 1;
+                        self.show_undone(&b, m);
+                        self.show_counter(at, total);
+                        self.w.addstr(": took back moving the ");
+                        self.show_move(&b, m, c);
+                    }
+                    None => {
+                        self.clear_status();
+                        self.w.addstr("This is the start of the game.");
+                    }
+                },
+                Key::Char('<') => at = self.go_to_move(&mut b, at, 0, total),
+                Key::Char('>') => {
+                    at = self.go_to_move(&mut b, at, total, total)
+                }
+                Key::Char('g') => {
+                    self.clear_prompt();
+                    self.w.addstr(&format!("Go to move (0 to {}): ", total));
+                    match self.get_number() {
+                        Ok(n) if n <= total => {
+                            at = self.go_to_move(&mut b, at, n, total)
+                        }
+                        Err(Key::Resize) => {
+                            at = self.go_to_move(&mut b, at, at, total)
+                        }
+                        Err(Key::Eof) => return None,
+                        _ => {
+                            self.show_error("Bad move number.");
+                        }
+                    }
+                }
+                Key::Char('l') => return Some(b),
+                Key::Char('q') | Key::Eof => return None,
+                _ => {
+                    self.show_error("Bad input.");
+                }
+            }
+        }
+    }
+
+    // Redo or undo moves until n moves have been made, and show the
+    // result.  Returns n.
+    fn go_to_move(
+        &mut self,
+        b: &mut Board,
+        at: usize,
+        n: usize,
+        total: usize,
+    ) -> usize {
+        for _ in n..at {
+            b.undo();
+        }
+        for _ in at..n {
+            b.redo();
+        }
+        self.show_game(b);
+        self.show_counter(n, total);
+        self.w.addch('.');
+        n
+    }
+
+    // Start the status line with the number of moves made
+    fn show_counter(&mut self, at: usize, total: usize) {
+        self.clear_status();
+        self.w.addstr(&format!("Move {} of {}", at, total));
+    }
+
+    // Read a number typed at the prompt.  Backspace erases a digit
+    // and the enter key ends the number.  Any other key is returned
+    // as an error, as is the enter key when no digits were typed.
+    fn get_number(&mut self) -> Result<usize, Key> {
+        let mut digits = String::new();
+        loop {
+            match self.get_key() {
+                Key::Char(c) if c.is_ascii_digit() && digits.len() < 4 => {
+                    self.w.addch(c);
+                    digits.push(c);
+                }
+                Key::Backspace if !digits.is_empty() => {
+                    let (y, x) = self.w.getyx();
+                    self.w.mov(y, x - 1);
+                    self.w.clrtoeol();
+                    digits.pop();
+                }
+                Key::Enter => return digits.parse().map_err(|_| Key::Enter),
+                key => return Err(key),
+            }
+        }
+    }
+
+    // Write the record of the game in a file, and report it on the
+    // status line.  The record of a finished game is written without
+    // asking.
+
+    fn record_game(&mut self, b: &Board, finish: Finish) {
+        let record = Record::new(b, finish).to_string();
+        match fs::write(RECORD_FILE_NAME, record) {
+            Ok(()) => {
+                self.w.addstr("Game recorded in ");
+                self.w.addstr(RECORD_FILE_NAME);
+                self.w.addch('.');
+            }
+            Err(_) => {
+                self.error_on();
+                self.w.addstr("Record error: Cannot write ");
+                self.w.addstr(RECORD_FILE_NAME);
+                self.w.addch('.');
+                self.end_error();
+            }
+        }
+    }
+
     // Help
 
     fn help(&mut self, b: &Board) -> bool {
@@ -351,31 +1370,36 @@ impl Screen {
         self.w.addstr(
             "
 
-There are eight stacks of cards and a foundation for each suit.  A
-card may be moved from the top of a stack to its foundation or to
-the top of another stack.  The object of the game is to order the
-cards in each stack so that each card is covered only by cards of
-lesser rank. The ace has the smallest rank and the king has the
-greatest rank.
+There are eight stacks of cards in the usual game, and a foundation
+for each suit.  A card may be moved from the top of a stack to its
+foundation or to the top of another stack.  The object of the game
+is to order the cards in each stack so that each card is covered
+only by cards of lesser rank. The ace has the smallest rank and the
+king has the greatest rank.
 
 A card may be moved to its foundation when the card's predecessor of
 the same suit is there.  A card may be moved to a stack when the top
 card of the stack has rank one greater than the card being moved.  A
-card can always be moved to an empty stack.
+card can always be moved to an empty stack or an empty reserve cell.
 
 Commands:                              Command Aliases:
-
-  0    Select a foundation.              <space> = 0,
-  1-8  Select a stack.                   j = 1, k = 2, l = 3, ; = 4,
+  0    Select a foundation.              <space> = 0, [ = 9, ] = A,
+  1-9  Select a stack, then A, B, ...    j = 1, k = 2, l = 3, ; = 4,
   q    Quit the game.                    u = 5, i = 6, o = 7, p = 8.
-  r    Restore a game from a file.
-  s    Save a game in a file.
-  ?    Print this help and then refresh screen.",
+  r    Restore a game from a file.       z, y Undo or redo a move.
+  s    Save a game in a file.            t    Toggle automatic play.
+  h    Suggest a move.                   ?    Print this help.
+  m    Move a run of cards.              w    Write a record of the game.
+  a-d  Select a reserve cell.            <-, -> Move cursor, enter selects.",
         );
         self.w.mov(self.prompt, 0);
         self.w.addstr("Type space for more about the program. ");
-        if self.w.getch() == ' ' {
-            self.show_auth();
+        let quit = match self.get_key() {
+            Key::Char(' ') => self.show_auth(),
+            key => key == Key::Eof,
+        };
+        if quit {
+            return true;
         }
         self.show_game(b);
         false
@@ -383,7 +1407,7 @@ Commands:                              Command Aliases:
 
     // Give additional hints and show author
 
-    fn show_auth(&mut self) {
+    fn show_auth(&mut self) -> bool {
         self.w.clear();
         self.w.addstr(
             "The program normally uses 52 cards or 13 ranks.  A full sized game is
@@ -391,9 +1415,14 @@ quite difficult, so beginners should play smaller games.  The number
 of ranks used in a game can be selected by quitting out of the current
 game and typing r at the restart game prompt.  Alternatively, the
 program can be given a command line argument specifying the number of
-ranks to be used.
-
-
+ranks to be used.  The number of each deal is shown in the title, and
+the --seed option starts the program with the deal with that number.
+The --variant option or v at the restart game prompt selects another
+game.  In Beleaguered Castle the aces start on the foundations, and in
+Citadel cards are also played to the foundations as they are dealt.
+In Fortress cards are built up or down in suit.  Chessboard is
+Fortress with ranks that wrap around, and the first card moved to a
+foundation sets the base rank of every foundation.
 
 Streets and Alleys version ",
         );
@@ -411,21 +1440,15 @@ implied warranty.",
         );
         self.w.mov(self.prompt, 0);
         self.w.addstr("Type any character to continue the game. ");
-        self.w.getch();
+        self.get_key() == Key::Eof
     }
 
     // Change the number of ranks used in a game
 
     // This view just needs a status and a prompt field
 
-    fn resize(&mut self) -> Resize {
-        self.w.clear();
-        self.w.mov(self.title, STACK_INDENT);
-        self.w.addstr("Streets and Alleys");
-        self.w.mov(self.status, 0);
-        self.w.addstr("Status:");
-        self.w.mov(self.prompt, 0);
-        self.w.addstr("Prompt:");
+    fn resize(&mut self, v: Variant) -> Resize {
+        self.show_frame(v);
         loop {
             self.clear_status();
             self.w
@@ -435,64 +1458,107 @@ implied warranty.",
                 "Press one of 5,..., 9, t, j, q, k \
                  to select the largest rank. ",
             );
-            match self.w.getch() {
-                '5' => return Rank(5),
-                '6' => return Rank(6),
-                '7' => return Rank(7),
-                '8' => return Rank(8),
-                '9' => return Rank(9),
-                't' => return Rank(10),
-                'j' => return Rank(11),
-                'q' => return Rank(12),
-                'k' => return Rank(13),
+            match self.get_key() {
+                Key::Char('5') => return Rank(5),
+                Key::Char('6') => return Rank(6),
+                Key::Char('7') => return Rank(7),
+                Key::Char('8') => return Rank(8),
+                Key::Char('9') => return Rank(9),
+                Key::Char('t') => return Rank(10),
+                Key::Char('j') => return Rank(11),
+                Key::Char('q') => return Rank(12),
+                Key::Char('k') => return Rank(13),
+                Key::Resize => {
+                    self.show_frame(v);
+                    continue;
+                }
+                Key::Eof => return Quit,
                 _ => (),
             }
-            self.clear_status();
-            self.w.addstr("Bad Input.");
+            self.show_error("Bad Input.");
             self.clear_prompt();
             self.w.addstr(
                 "Type space to try again, \
                  x to exit program, others play game. ",
             );
-            match self.w.getch() {
-                ' ' => continue,
-                'x' => return Quit,
+            match self.get_key() {
+                Key::Resize => self.show_frame(v),
+                Key::Char(' ') => continue,
+                Key::Char('x') | Key::Eof => return Quit,
                 _ => return Again,
             }
         }
     }
-}
 
-// Translate between ints and chars
-
-fn i2char(i: i32) -> char {
-    match i {
-        0 => '0',
-        1 => '1',
-        2 => '2',
-        3 => '3',
-        4 => '4',
-        5 => '5',
-        6 => '6',
-        7 => '7',
-        8 => '8',
-        9 => '9',
-        _ => panic!("Bad integer for i2char"),
+    // The title names the variant being played.
+    fn show_frame(&mut self, v: Variant) {
+        self.w.clear();
+        self.w.mov(self.title, STACK_INDENT);
+        self.w.addstr(v.name());
+        self.w.mov(self.status, 0);
+        self.w.addstr("Status:");
+        self.w.mov(self.prompt, 0);
+        self.w.addstr("Prompt:");
+    }
+
+    // Change the variant played
+
+    fn show_variants(&mut self, v: Variant) {
+        self.show_frame(v);
+        for (i, v) in Variant::ALL.iter().enumerate() {
+            self.w.mov(self.title + 2 + i as i32, STACK_INDENT);
+            self.w.addstr(&format!("{}  {}", i + 1, v.name()));
+        }
     }
+
+    fn choose_variant(&mut self, v: Variant) -> Choice {
+        self.show_variants(v);
+        loop {
+            self.clear_status();
+            self.w.addstr("Changing the variant played.");
+            self.clear_prompt();
+            self.w.addstr(&format!(
+                "Press one of 1,..., {} to select the variant. ",
+                Variant::ALL.len()
+            ));
+            let i = match self.get_key() {
+                Key::Resize => {
+                    self.show_variants(v);
+                    continue;
+                }
+                Key::Eof => return Choice::Quit,
+                Key::Char(c) => c.to_digit(10).unwrap_or(0) as usize,
+                _ => 0,
+            };
+            if let Some(chosen) = i.checked_sub(1).and_then(Variant::new) {
+                return Choice::Game(chosen);
+            }
+            self.show_error("Bad Input.");
+            self.clear_prompt();
+            self.w.addstr(
+                "Type space to try again, \
+                 x to exit program, others play game. ",
+            );
+            match self.get_key() {
+                Key::Resize => self.show_variants(v),
+                Key::Char(' ') => continue,
+                Key::Char('x') | Key::Eof => return Choice::Quit,
+                _ => return Choice::Again,
+            }
+        }
+    }
+}
+
+// Number of positions of the cursor in cursor mode
+fn positions(b: &Board) -> usize {
+    b.reserves() + b.stacks() + 1
 }
 
-fn char2u(c: char) -> usize {
-    match c {
-        '0' => 0,
-        '1' => 1,
-        '2' => 2,
-        '3' => 3,
-        '4' => 4,
-        '5' => 5,
-        '6' => 6,
-        '7' => 7,
-        '8' => 8,
-        '9' => 9,
-        _ => panic!("Bad character for char2u"),
+// The name of a place used on the status line
+fn place_name(b: &Board, p: usize) -> String {
+    if b.is_reserve(p) {
+        format!("reserve cell {}", b.place_label(p))
+    } else {
+        format!("stack {}", b.place_label(p))
     }
 }
@@ -0,0 +1,273 @@
+//! # A Streets and Alleys solver
+
+//! This module searches for a sequence of moves that wins a game.
+//! The search is depth first.  It remembers every position it has
+//! seen so that no position is explored twice, and it gives up when
+//! it has explored too many positions or used too much memory.
+
+// Copyright (c) 2019 John D. Ramsdell
+// Permission to use, copy, modify, and distribute this software and its
+// documentation for any purpose and without fee is hereby granted,
+// provided that the above copyright notice appear in all copies.  John
+// Ramsdell makes no representations about the suitability of this
+// software for any purpose.  It is provided "as is" without express or
+// implied warranty.
+
+use super::board::*;
+use std::collections::HashSet;
+use std::mem;
+use std::time::{Duration, Instant};
+
+/// Bounds on the work done by a search
+#[derive(Clone, Copy, Debug)]
+pub struct Limits {
+    /// Largest number of positions explored
+    pub nodes: usize,
+    /// Largest number of bytes used to remember positions
+    pub memory: usize,
+    /// Longest time spent searching, if any
+    pub time: Option<Duration>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            nodes: 2_000_000,
+            memory: 256 << 20,
+            time: None,
+        }
+    }
+}
+
+// Number of positions explored between looks at the clock
+const CLOCK_INTERVAL: usize = 1024;
+
+/// The result of a search
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// The moves that win the game
+    Solved(Vec<Move>),
+    /// Every position reachable from the board has been explored,
+    /// and none of them are won.
+    Unwinnable,
+    /// The search reached one of its limits
+    GaveUp,
+}
+
+// A position reached during a search along with the moves from it
+// that have yet to be tried
+struct Frame {
+    moves: Vec<Move>,
+    next: usize,
+}
+
+/// Search for a sequence of moves that wins the game on a board
+///
+/// The board is not changed.
+pub fn solve(b: &Board, limits: &Limits) -> Outcome {
+    let deadline = limits.time.map(|t| Instant::now() + t);
+    let mut b = b.clone();
+    let mut seen = HashSet::new();
+    let mut memory = 0;
+    let mut path = Vec::new();
+
+    let key = position(&b);
+    memory += footprint(&key);
+    seen.insert(key);
+    let mut frames = vec![Frame {
+        moves: candidates(&b),
+        next: 0,
+    }];
+
+    loop {
+        if b.is_done() {
+            return Outcome::Solved(path);
+        }
+        let m = match frames.last_mut() {
+            None => return Outcome::Unwinnable,
+            Some(f) if f.next < f.moves.len() => {
+                f.next += 1;
+                f.moves[f.next - 1]
+            }
+            Some(_) => {
+                // All moves from this position have been tried
+                frames.pop();
+                if path.pop().is_some() {
+                    b.undo();
+                }
+                continue;
+            }
+        };
+        b.make_move(m);
+        let key = position(&b);
+        if seen.contains(&key) {
+            b.undo();
+            continue;
+        }
+        memory += footprint(&key);
+        seen.insert(key);
+        if seen.len() > limits.nodes || memory > limits.memory {
+            return Outcome::GaveUp;
+        }
+        if seen.len() % CLOCK_INTERVAL == 0
+            && deadline.is_some_and(|d| Instant::now() > d)
+        {
+            return Outcome::GaveUp;
+        }
+        path.push(m);
+        frames.push(Frame {
+            moves: candidates(&b),
+            next: 0,
+        });
+    }
+}
+
+// The moves worth trying from a position, most promising first
+//
+// When a card can be moved to its foundation and the move can never
+// hurt, that move is the only one tried.  Otherwise, moves
+// to the foundations come first, then moves onto cards, then moves
+// to an empty stack, and finally moves to a reserve cell.  Since all
+// empty stacks are alike, only the first one is used, and a card
+// that is alone in its stack is never moved to an empty stack.  The
+// same goes for reserve cells, and a card is never moved from one
+// reserve cell to another.
+fn candidates(b: &Board) -> Vec<Move> {
+    if let Some(m) = b.safe_move() {
+        return vec![m];
+    }
+    let first = (0..b.stacks()).find(|s| b.stack_len(*s) == 0);
+    let cell = (b.stacks()..b.places()).find(|p| b.last_card(*p).is_none());
+    let mut moves = Vec::new();
+    let mut empty = Vec::new();
+    let mut reserve = Vec::new();
+    for m in b.legal_moves() {
+        match m {
+            Move::Foundation(_) => moves.push(m),
+            Move::Stack(from, to) if b.is_reserve(to) => {
+                if !b.is_reserve(from) && cell == Some(to) {
+                    reserve.push(m);
+                }
+            }
+            Move::Stack(from, to) if b.stack_len(to) == 0 => {
+                let alone = !b.is_reserve(from) && b.stack_len(from) == 1;
+                if !alone && first == Some(to) {
+                    empty.push(m);
+                }
+            }
+            Move::Stack(..) => moves.push(m),
+        }
+    }
+    moves.append(&mut empty);
+    moves.append(&mut reserve);
+    moves
+}
+
+// A position is identified by its foundations, its stacks, and its
+// reserve cells.  The order of the stacks does not matter, so they
+// are sorted, and so are the cards in the reserve cells.  The
+// foundations are sorted too, since with more than one deck, the
+// foundations of a suit are interchangeable.
+fn position(b: &Board) -> Vec<u8> {
+    let mut stacks: Vec<&[Card]> = (0..b.stacks())
+        .map(|s| b.stack_iter(s).as_slice())
+        .collect();
+    stacks.sort_unstable();
+    let cards = b.decks() * b.ranks() * b.suits();
+    let mut key = Vec::with_capacity(b.foundations() + b.places() + cards);
+    let mut foundations: Vec<u8> = (0..b.foundations())
+        .map(|f| {
+            let empty = b.foundation_suit(f).index() as u8;
+            b.foundation_ref(f).map_or(empty, |c| c.code())
+        })
+        .collect();
+    foundations.sort_unstable();
+    key.extend(foundations);
+    for s in stacks {
+        key.extend(s.iter().map(|c| c.code()));
+        key.push(u8::MAX); // Marks the end of a stack
+    }
+    let mut cells: Vec<u8> = (b.stacks()..b.places())
+        .map(|p| b.last_card(p).map_or(0, |c| c.code()))
+        .collect();
+    cells.sort_unstable();
+    key.extend(cells);
+    key
+}
+
+// An estimate of the memory used to remember a position
+fn footprint(key: &[u8]) -> usize {
+    key.len() + 2 * mem::size_of::<Vec<u8>>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn small() -> Limits {
+        Limits {
+            nodes: 5_000,
+            ..Limits::default()
+        }
+    }
+
+    fn deal(ranks: usize, n: u32) -> Board {
+        let mut b = Board::new(Variant::StreetsAndAlleys, 4, 8, ranks);
+        b.deal(n);
+        b
+    }
+
+    // For each rank count, a deal the solver wins within small limits
+    const WINNABLE: [(usize, u32); 9] = [
+        (5, 1),
+        (6, 1),
+        (7, 1),
+        (8, 1),
+        (9, 1),
+        (10, 5),
+        (11, 2),
+        (12, 1),
+        (13, 12),
+    ];
+
+    #[test]
+    fn solutions_win_for_every_rank_count() {
+        for &(ranks, n) in WINNABLE.iter() {
+            let mut b = deal(ranks, n);
+            match solve(&b, &small()) {
+                Outcome::Solved(moves) => {
+                    for m in moves {
+                        b.apply(m).unwrap_or_else(|e| {
+                            panic!("{} ranks, deal {}: {}", ranks, n, e)
+                        });
+                    }
+                    assert!(b.is_done(), "{} ranks, deal {}", ranks, n);
+                }
+                o => panic!("{} ranks, deal {}: {:?}", ranks, n, o),
+            }
+        }
+    }
+
+    // Once the ace goes to its foundation, the five is stuck on top of
+    // the other cards of the only stack.
+    #[test]
+    fn stuck_stack_is_unwinnable() {
+        let b: Board = "streets 5\n0 C-\n1 C2 C3 C4 C5 CA\n".parse().unwrap();
+        assert_eq!(solve(&b, &small()), Outcome::Unwinnable);
+    }
+
+    #[test]
+    fn tight_limits_give_up() {
+        let b = deal(13, 3);
+        let nodes = Limits {
+            nodes: 100,
+            ..Limits::default()
+        };
+        assert_eq!(solve(&b, &nodes), Outcome::GaveUp);
+        let memory = Limits {
+            memory: 1_000,
+            ..Limits::default()
+        };
+        assert_eq!(solve(&b, &memory), Outcome::GaveUp);
+    }
+}
@@ -14,16 +14,122 @@
 extern crate ncurses;
 
 use ncurses::*;
+use std::panic;
+
+/// A key read by [`Window::getch`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Key {
+    /// An ASCII character other than those named below
+    Char(char),
+    /// The enter or return key
+    Enter,
+    /// The backspace or delete key
+    Backspace,
+    /// Arrow keys
+    Up,
+    Down,
+    Left,
+    Right,
+    /// Home key
+    Home,
+    /// End key
+    End,
+    /// A function key numbered from one
+    Function(u8),
+    /// The terminal has been resized
+    Resize,
+    /// No more input can be read, as when the terminal is closed
+    Eof,
+    /// Any other key
+    Other(i32),
+}
+
+/// A colour used for the characters of a colour pair
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+}
+
+impl Color {
+    fn code(self) -> i16 {
+        match self {
+            Color::Black => COLOR_BLACK,
+            Color::Red => COLOR_RED,
+            Color::Green => COLOR_GREEN,
+            Color::Yellow => COLOR_YELLOW,
+            Color::Blue => COLOR_BLUE,
+            Color::Magenta => COLOR_MAGENTA,
+            Color::Cyan => COLOR_CYAN,
+            Color::White => COLOR_WHITE,
+        }
+    }
+}
+
+/// An attribute used when writing characters
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Attr {
+    /// The terminal's best highlighting mode
+    Standout,
+    /// Reverse video
+    Reverse,
+    /// Extra bright or bold
+    Bold,
+    /// A colour pair set up by [`Window::init_pair`]
+    Pair(i16),
+}
+
+impl Attr {
+    fn code(self) -> attr_t {
+        match self {
+            Attr::Standout => A_STANDOUT(),
+            Attr::Reverse => A_REVERSE(),
+            Attr::Bold => A_BOLD(),
+            Attr::Pair(n) => COLOR_PAIR(n),
+        }
+    }
+}
+
+/// The attributes in use, saved so they can be put back
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Attrs(attr_t);
 
 /// The struct that holds a curses wndow
 pub struct Window {
     w: WINDOW,
+    utf8: bool, // Does the locale encode characters in UTF-8?
 }
 
 impl Window {
     /// Create a window
+    ///
+    /// The locale is taken from the environment before curses starts
+    /// so that wide characters can be written.  The terminal is
+    /// restored when the window is dropped, and before the message of
+    /// a panic is printed.
     pub fn new() -> Window {
-        Window { w: initscr() }
+        setlocale(LcCategory::all, "");
+        let ctype = setlocale(LcCategory::ctype, "").to_ascii_lowercase();
+        let utf8 = ctype.contains("utf-8") || ctype.contains("utf8");
+        let hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            ncurses::endwin();
+            hook(info)
+        }));
+        let w = initscr();
+        keypad(w, true);
+        Window { w, utf8 }
+    }
+
+    /// Can characters outside of ASCII be written?
+    pub fn is_wide(&self) -> bool {
+        self.utf8
     }
 
     /// Get the height of the window
@@ -31,6 +137,16 @@ impl Window {
         ncurses::getmaxy(self.w)
     }
 
+    /// Get the width of the window
+    pub fn getmaxx(&self) -> i32 {
+        ncurses::getmaxx(self.w)
+    }
+
+    /// Get the position of the cursor as (y, x)
+    pub fn getyx(&self) -> (i32, i32) {
+        (getcury(self.w), getcurx(self.w))
+    }
+
     /// Move cursor
     pub fn mov(&self, y: i32, x: i32) -> i32 {
         wmove(self.w, y, x)
@@ -41,11 +157,49 @@ impl Window {
         waddch(self.w, ch as chtype)
     }
 
+    /// Write a character that may be wide at the cursor
+    ///
+    /// The wide curses library decodes the character from its bytes
+    /// in UTF-8, so this is only useful when [`Window::is_wide`].
+    pub fn add_wch(&self, ch: char) -> i32 {
+        waddstr(self.w, ch.encode_utf8(&mut [0; 4]))
+    }
+
     /// Write a string at the cursor
     pub fn addstr(&self, s: &str) -> i32 {
         waddstr(self.w, s)
     }
 
+    /// Start writing characters with an attribute
+    ///
+    /// Turning on a colour pair replaces the one in use.
+    pub fn attron(&self, a: Attr) -> i32 {
+        wattron(self.w, a.code())
+    }
+
+    /// Stop writing characters with an attribute
+    pub fn attroff(&self, a: Attr) -> i32 {
+        wattroff(self.w, a.code())
+    }
+
+    /// Return the attributes in use
+    pub fn attr_get(&self) -> Attrs {
+        let mut attrs = 0;
+        let mut pair = 0;
+        wattr_get(self.w, &mut attrs, &mut pair);
+        Attrs(attrs | COLOR_PAIR(pair))
+    }
+
+    /// Use the attributes saved by [`Window::attr_get`]
+    pub fn attrset(&self, a: Attrs) -> i32 {
+        wattrset(self.w, a.0)
+    }
+
+    /// Show the changes made to the window
+    pub fn refresh(&self) -> i32 {
+        wrefresh(self.w)
+    }
+
     /// Clear window
     pub fn clear(&self) -> i32 {
         wclear(self.w)
@@ -56,16 +210,30 @@ impl Window {
         wclrtoeol(self.w)
     }
 
-    /// Get an ASCII char
-    pub fn getch(&self) -> char {
-        let i = wgetch(self.w);
-        if i < 0 || i >= 128 {
-            panic!("Non-ASCII in getch")
+    /// Get a key
+    pub fn getch(&self) -> Key {
+        match wgetch(self.w) {
+            ERR => Key::Eof,
+            10 | 13 | KEY_ENTER => Key::Enter,
+            8 | 127 | KEY_BACKSPACE => Key::Backspace,
+            KEY_UP => Key::Up,
+            KEY_DOWN => Key::Down,
+            KEY_LEFT => Key::Left,
+            KEY_RIGHT => Key::Right,
+            KEY_HOME => Key::Home,
+            KEY_END => Key::End,
+            KEY_RESIZE => Key::Resize,
+            i if i > KEY_F0 && i <= KEY_F0 + 63 => {
+                Key::Function((i - KEY_F0) as u8)
+            }
+            i @ 0..=127 => Key::Char(i as u8 as char),
+            i => Key::Other(i),
         }
-        // Hack alert!
-        // Convert i32 to char through u8
-        let u = i as u8;
-        u as char
+    }
+
+    /// Sleep for some milliseconds
+    pub fn napms(ms: i32) -> i32 {
+        ncurses::napms(ms)
     }
 
     /// Disable line buffering and erase/kill character-processing
@@ -78,12 +246,36 @@ impl Window {
         ncurses::noecho()
     }
 
+    /// Start using colour if the terminal has it
+    ///
+    /// Colour pairs are drawn on the terminal's own background.
+    /// Returns false for a terminal without colour.
+    pub fn start_color() -> bool {
+        if !has_colors() {
+            return false;
+        }
+        ncurses::start_color();
+        use_default_colors();
+        true
+    }
+
+    /// Set up a colour pair that draws characters in a colour
+    pub fn init_pair(pair: i16, fg: Color) -> i32 {
+        ncurses::init_pair(pair, fg.code(), -1)
+    }
+
     /// Close window
     pub fn endwin() -> i32 {
         ncurses::endwin()
     }
 }
 
+impl Drop for Window {
+    fn drop(&mut self) {
+        ncurses::endwin();
+    }
+}
+
 impl Default for Window {
     fn default() -> Self {
         Self::new()